@@ -0,0 +1,241 @@
+#![allow(clippy::missing_errors_doc)]
+
+use crate::{
+    collation::{pack_weights, unpack_weights},
+    error::{Result, SourceLine},
+    paths::read_input,
+};
+use std::{
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+/// The contents of an `allkeys.txt` or `allkeys_CLDR.txt` file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CollationElementTable {
+    pub path: PathBuf,
+    /// The `@version` header, if present
+    pub version: Option<String>,
+    pub implicit_weights: Vec<ImplicitWeights>,
+    /// Mappings in file order
+    pub entries: Vec<CollationEntry>,
+}
+
+/// An `@implicitweights` line, giving the base primary for a range of code points
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImplicitWeights {
+    pub range: RangeInclusive<u32>,
+    pub base: u16,
+    pub name: String,
+}
+
+/// A mapping from one or more code points to their collation elements
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CollationEntry {
+    pub code_points: Box<[u32]>,
+    /// Never empty
+    pub elements: Box<[CollationElement]>,
+    /// The character name from the trailing comment
+    pub name: String,
+    /// One-based line number in the source file
+    pub line: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CollationElement {
+    pub variable: bool,
+    pub primary: u16,
+    pub secondary: u16,
+    pub tertiary: u16,
+}
+
+impl CollationElement {
+    #[must_use]
+    pub const fn pack(self) -> u32 {
+        pack_weights(self.variable, self.primary, self.secondary, self.tertiary)
+    }
+
+    #[must_use]
+    pub const fn unpack(packed: u32) -> Self {
+        let (variable, primary, secondary, tertiary) = unpack_weights(packed);
+        Self {
+            variable,
+            primary,
+            secondary,
+            tertiary,
+        }
+    }
+}
+
+impl CollationElementTable {
+    pub fn read(path: &Path) -> Result<Self> {
+        let data = read_input(path)?;
+        Self::parse(path, &data)
+    }
+
+    /// Parses the text of an allkeys file; `path` is used only for error messages
+    pub fn parse(path: &Path, data: &str) -> Result<Self> {
+        let mut version = None;
+        let mut implicit_weights = Vec::new();
+        let mut entries = Vec::new();
+
+        for (i, line) in data.lines().enumerate() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let source = SourceLine::new(path, i);
+            let (content, name) = line.split_once('#').unwrap_or((line, ""));
+            let name = name.trim().to_string();
+
+            if let Some(directive) = content.strip_prefix('@') {
+                match directive.split_once(' ') {
+                    Some(("version", v)) => version = Some(v.trim().to_string()),
+                    Some(("implicitweights", rest)) => {
+                        implicit_weights.push(parse_implicit_weights(source, rest, name)?);
+                    }
+                    _ => return Err(source.error(format!("unknown directive: @{directive}"))),
+                }
+                continue;
+            }
+
+            let (left_of_semicolon, right_of_semicolon) = content
+                .split_once(';')
+                .ok_or_else(|| source.error("missing semicolon"))?;
+
+            let code_points = left_of_semicolon
+                .split_whitespace()
+                .map(|s| source.hex_u32(s))
+                .collect::<Result<Box<[u32]>>>()?;
+            if code_points.is_empty() {
+                return Err(source.error("no code points"));
+            }
+
+            let elements = parse_elements(source, right_of_semicolon.trim())?;
+
+            entries.push(CollationEntry {
+                code_points,
+                elements,
+                name,
+                line: source.line,
+            });
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            version,
+            implicit_weights,
+            entries,
+        })
+    }
+
+    pub(crate) fn source(&self, entry: &CollationEntry) -> SourceLine<'_> {
+        SourceLine {
+            file: &self.path,
+            line: entry.line,
+        }
+    }
+}
+
+// E.g. "17000..18AFF; FB00"
+fn parse_implicit_weights(source: SourceLine, s: &str, name: String) -> Result<ImplicitWeights> {
+    let malformed = || source.error(format!("malformed @implicitweights: {s}"));
+
+    let (range, base) = s.split_once(';').ok_or_else(malformed)?;
+    let (first, last) = range.trim().split_once("..").ok_or_else(malformed)?;
+
+    Ok(ImplicitWeights {
+        range: source.hex_u32(first)?..=source.hex_u32(last)?,
+        base: source.hex_u16(base.trim())?,
+        name,
+    })
+}
+
+// E.g. "[.1FA0.0020.0002][.0000.0111.0002]"
+fn parse_elements(source: SourceLine, s: &str) -> Result<Box<[CollationElement]>> {
+    let mut elements = Vec::new();
+    let mut rest = s;
+
+    while !rest.is_empty() {
+        let (element, after) = rest
+            .strip_prefix('[')
+            .and_then(|r| r.split_once(']'))
+            .ok_or_else(|| source.error(format!("malformed collation elements: {s}")))?;
+
+        let malformed = || source.error(format!("malformed collation element: [{element}]"));
+
+        let variable = match element.chars().next() {
+            Some('*') => true,
+            Some('.') => false,
+            _ => return Err(malformed()),
+        };
+
+        let mut weights = element[1..].split('.');
+        let mut next_weight = || {
+            weights
+                .next()
+                .ok_or_else(malformed)
+                .and_then(|w| source.hex_u16(w))
+        };
+
+        elements.push(CollationElement {
+            variable,
+            primary: next_weight()?,
+            secondary: next_weight()?,
+            tertiary: next_weight()?,
+        });
+
+        if weights.next().is_some() {
+            return Err(malformed());
+        }
+
+        rest = after.trim_start();
+    }
+
+    if elements.is_empty() {
+        return Err(source.error("no collation elements"));
+    }
+
+    Ok(elements.into_boxed_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_lines() {
+        let data = "\
+# allkeys-16.0.0.txt
+@version 16.0.0
+
+@implicitweights 1B170..1B2FF; FB01 # Nushu
+
+0020  ; [*0209.0020.0002] # SPACE
+0061 0301 ; [.2380.0020.0002][.0000.0024.0002] # LATIN SMALL LETTER A WITH ACUTE
+";
+        let table = CollationElementTable::parse(Path::new("allkeys.txt"), data).unwrap();
+
+        assert_eq!(table.version.as_deref(), Some("16.0.0"));
+        assert_eq!(table.implicit_weights[0].range, 0x1B170..=0x1B2FF);
+        assert_eq!(table.implicit_weights[0].base, 0xFB01);
+
+        let entry = &table.entries[1];
+        assert_eq!(*entry.code_points, [0x61, 0x301]);
+        assert_eq!(entry.name, "LATIN SMALL LETTER A WITH ACUTE");
+        assert_eq!(entry.line, 7);
+        assert_eq!(
+            entry.elements[1],
+            CollationElement {
+                variable: false,
+                primary: 0,
+                secondary: 0x24,
+                tertiary: 2,
+            }
+        );
+        assert!(table.entries[0].elements[0].variable);
+
+        assert!(CollationElementTable::parse(Path::new("x"), "0020 ; [*0209.0020]").is_err());
+        assert!(CollationElementTable::parse(Path::new("x"), "0020 ; # SPACE").is_err());
+    }
+}
@@ -0,0 +1,426 @@
+#![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
+
+use crate::{
+    diff::{TableKind, list_outputs},
+    error::{Error, Result},
+    normalization::{DecompTable, FcdTable},
+    paths::{Paths, read_table, write_output},
+    trie::{CollationTrieTable, ContractionEdge, ContractionMeta, PrefixMapping},
+    variable::VariableTable,
+};
+use std::path::Path;
+
+const MAGIC: &[u8; 6] = b"FERUCA";
+const FORMAT_VERSION: u8 = 3;
+const HEADER_LEN: usize = 16;
+const DIRECTORY_ENTRY_LEN: usize = 16;
+const ALIGN: usize = 8;
+
+/// A table with a fixed little-endian layout, which feruca can use in place from
+/// `include_bytes!` without deserializing anything
+///
+/// Every archive starts with a 16-byte header: the magic bytes `FERUCA`, a byte for the kind of
+/// table, a format version byte (currently 3), the number of sections as a `u32`, and four zero
+/// bytes. A directory follows, with the byte offset and byte length of each section as two
+/// `u64`s. Each section is a plain array of integers, starting at a multiple of eight bytes from
+/// the start of the file and padded with zeros to the next one.
+///
+/// All integers are little-endian. To view a section as a `&'static [u64]`, the archive has to
+/// be included at an eight-byte alignment, e.g. through a `#[repr(align(8))]` wrapper struct.
+pub trait Archived: Sized {
+    const KIND: u8;
+    /// The byte length of one element of each section, so also the number of sections
+    const ELEMENT_LENS: &'static [usize];
+
+    fn to_sections(&self) -> Vec<Vec<u8>>;
+
+    /// Called only with the right number of sections, each a whole number of elements
+    fn from_sections(sections: &[&[u8]]) -> Result<Self, String>;
+
+    #[must_use]
+    fn to_archive(&self) -> Vec<u8> {
+        let sections = self.to_sections();
+        assert_eq!(sections.len(), Self::ELEMENT_LENS.len());
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(Self::KIND);
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&u32::try_from(sections.len()).unwrap().to_le_bytes());
+        bytes.extend_from_slice(&[0; 4]);
+
+        let mut offset = HEADER_LEN + sections.len() * DIRECTORY_ENTRY_LEN;
+        for section in &sections {
+            bytes.extend_from_slice(&(offset as u64).to_le_bytes());
+            bytes.extend_from_slice(&(section.len() as u64).to_le_bytes());
+            offset += section.len().next_multiple_of(ALIGN);
+        }
+
+        for section in &sections {
+            bytes.extend_from_slice(section);
+            bytes.resize(bytes.len().next_multiple_of(ALIGN), 0);
+        }
+
+        bytes
+    }
+
+    fn from_archive(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < HEADER_LEN || &bytes[..6] != MAGIC {
+            return Err("not an archived table".to_string());
+        }
+        if bytes[6] != Self::KIND {
+            return Err(format!("wrong kind of table: {}", char::from(bytes[6])));
+        }
+        if bytes[7] != FORMAT_VERSION {
+            return Err(format!("unknown format version: {}", bytes[7]));
+        }
+
+        let count = usize::try_from(read_u32(&bytes[8..12])).unwrap();
+        if count != Self::ELEMENT_LENS.len() {
+            return Err(format!(
+                "expected {} sections, found {count}",
+                Self::ELEMENT_LENS.len()
+            ));
+        }
+
+        let mut sections = Vec::with_capacity(count);
+        for i in 0..count {
+            let entry = HEADER_LEN + i * DIRECTORY_ENTRY_LEN;
+            let field = |at: usize| {
+                bytes
+                    .get(entry + at..entry + at + 8)
+                    .and_then(|field| usize::try_from(read_u64(field)).ok())
+            };
+            let (Some(offset), Some(len)) = (field(0), field(8)) else {
+                return Err("truncated section directory".to_string());
+            };
+
+            if offset % ALIGN != 0 {
+                return Err(format!("section {i} is misaligned"));
+            }
+            let section = offset
+                .checked_add(len)
+                .and_then(|end| bytes.get(offset..end))
+                .ok_or_else(|| format!("section {i} is out of bounds"))?;
+            if len % Self::ELEMENT_LENS[i] != 0 {
+                return Err(format!("section {i} has a partial element"));
+            }
+            sections.push(section);
+        }
+
+        Self::from_sections(&sections)
+    }
+}
+
+/// Sections: the page index (`u16`), the entries (`u64`), the contraction metadata (`u64`: first
+/// edge in bits 0–31, edge count in bits 32–47, maximum length in bits 48–55, non-starter
+/// flag in bit 56), the edges (five `u32`s each: code point, first child edge, weight start, child
+/// edge count in the low 16 bits and weight count in the high 16, then combining class in the low 8
+/// bits and the next edges' non-starter flag in bit 8), the prefix mappings (four `u32`s each:
+/// start in the prefix code point pool, code point count in the low 16 bits and prefix length in
+/// the high 16, weight start, weight count), the prefix code point pool (`u32`: each mapping's code
+/// points, then its prefix), and the weights (`u32`)
+impl Archived for CollationTrieTable {
+    const KIND: u8 = b'T';
+    const ELEMENT_LENS: &'static [usize] = &[2, 8, 8, 20, 16, 4, 4];
+
+    fn to_sections(&self) -> Vec<Vec<u8>> {
+        let meta: Vec<u64> = (self.contraction_meta.iter())
+            .zip(&self.non_starters)
+            .map(|(meta, &non_starters)| {
+                u64::from(meta.first_edge)
+                    | (u64::from(meta.edge_len) << 32)
+                    | (u64::from(meta.max_len) << 48)
+                    | (u64::from(non_starters) << 56)
+            })
+            .collect();
+        let edges: Vec<u32> = (self.edges.iter())
+            .zip(self.edge_cccs.iter().zip(&self.next_non_starters))
+            .flat_map(|(edge, (&ccc, &next_non_starters))| {
+                [
+                    edge.code_point,
+                    edge.next_first_edge,
+                    edge.weight_start,
+                    u32::from(edge.next_edge_len) | (u32::from(edge.weight_len) << 16),
+                    u32::from(ccc) | (u32::from(next_non_starters) << 8),
+                ]
+            })
+            .collect();
+
+        let mut prefixes = Vec::new();
+        let mut prefix_code_points = Vec::new();
+        for mapping in &self.prefixes {
+            prefixes.extend([
+                u32::try_from(prefix_code_points.len()).unwrap(),
+                u32::try_from(mapping.code_points.len() | (mapping.prefix.len() << 16)).unwrap(),
+                mapping.weight_start,
+                u32::from(mapping.weight_len),
+            ]);
+            prefix_code_points.extend_from_slice(&mapping.code_points);
+            prefix_code_points.extend_from_slice(&mapping.prefix);
+        }
+
+        vec![
+            le_u16s(&self.page_index),
+            le_u64s(&self.entries),
+            le_u64s(&meta),
+            le_u32s(&edges),
+            le_u32s(&prefixes),
+            le_u32s(&prefix_code_points),
+            le_u32s(&self.weights),
+        ]
+    }
+
+    fn from_sections(sections: &[&[u8]]) -> Result<Self, String> {
+        let meta = read_u64s(sections[2]);
+        let contraction_meta = meta
+            .iter()
+            .map(|&meta| ContractionMeta {
+                first_edge: (meta & 0xFFFF_FFFF) as u32,
+                edge_len: ((meta >> 32) & 0xFFFF) as u16,
+                max_len: ((meta >> 48) & 0xFF) as u8,
+            })
+            .collect();
+        let edges = read_u32s(sections[3]);
+        let edges = edges.chunks_exact(5);
+        let edge_cccs = edges.clone().map(|edge| (edge[4] & 0xFF) as u8).collect();
+        let next_non_starters = edges.clone().map(|edge| (edge[4] >> 8) & 1 == 1).collect();
+        let edges = edges
+            .map(|edge| ContractionEdge {
+                code_point: edge[0],
+                next_first_edge: edge[1],
+                weight_start: edge[2],
+                next_edge_len: (edge[3] & 0xFFFF) as u16,
+                weight_len: (edge[3] >> 16) as u16,
+            })
+            .collect();
+        let pool = read_u32s(sections[5]);
+        let prefixes = read_u32s(sections[4])
+            .chunks_exact(4)
+            .map(|mapping| {
+                let start = usize::try_from(mapping[0]).unwrap();
+                let len = usize::try_from(mapping[1] & 0xFFFF).unwrap();
+                let prefix_len = usize::try_from(mapping[1] >> 16).unwrap();
+                let Some(code_points) = pool.get(start..start + len + prefix_len) else {
+                    return Err(format!("prefix mapping at {start} is out of bounds"));
+                };
+                let (code_points, prefix) = code_points.split_at(len);
+                Ok(PrefixMapping {
+                    prefix: prefix.into(),
+                    code_points: code_points.into(),
+                    weight_start: mapping[2],
+                    weight_len: (mapping[3] & 0xFFFF) as u16,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            page_index: read_u16s(sections[0]),
+            entries: read_u64s(sections[1]),
+            contraction_meta,
+            edges,
+            weights: read_u32s(sections[6]),
+            non_starters: meta.iter().map(|&meta| (meta >> 56) & 1 == 1).collect(),
+            edge_cccs,
+            next_non_starters,
+            prefixes,
+        })
+    }
+}
+
+/// Sections: the page index (`u16`), the entries (`u64`) and the decomposition values (`u32`)
+impl Archived for DecompTable {
+    const KIND: u8 = b'D';
+    const ELEMENT_LENS: &'static [usize] = &[2, 8, 4];
+
+    fn to_sections(&self) -> Vec<Vec<u8>> {
+        vec![
+            le_u16s(&self.page_index),
+            le_u64s(&self.entries),
+            le_u32s(&self.values),
+        ]
+    }
+
+    fn from_sections(sections: &[&[u8]]) -> Result<Self, String> {
+        Ok(Self {
+            page_index: read_u16s(sections[0]),
+            entries: read_u64s(sections[1]),
+            values: read_u32s(sections[2]),
+        })
+    }
+}
+
+/// Sections: the page index (`u16`) and the pages (`u16`)
+impl Archived for FcdTable {
+    const KIND: u8 = b'F';
+    const ELEMENT_LENS: &'static [usize] = &[2, 2];
+
+    fn to_sections(&self) -> Vec<Vec<u8>> {
+        vec![le_u16s(&self.page_index), le_u16s(&self.pages)]
+    }
+
+    fn from_sections(sections: &[&[u8]]) -> Result<Self, String> {
+        Ok(Self {
+            page_index: read_u16s(sections[0]),
+            pages: read_u16s(sections[1]),
+        })
+    }
+}
+
+/// Sections: the page index (`u16`) and the pages (`u64`)
+impl Archived for VariableTable {
+    const KIND: u8 = b'V';
+    const ELEMENT_LENS: &'static [usize] = &[2, 8];
+
+    fn to_sections(&self) -> Vec<Vec<u8>> {
+        vec![le_u16s(&self.page_index), le_u64s(&self.pages)]
+    }
+
+    fn from_sections(sections: &[&[u8]]) -> Result<Self, String> {
+        Ok(Self {
+            page_index: read_u16s(sections[0]),
+            pages: read_u64s(sections[1]),
+        })
+    }
+}
+
+fn le_u16s(values: &[u16]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn le_u32s(values: &[u32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn le_u64s(values: &[u64]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn read_u16s(bytes: &[u8]) -> Box<[u16]> {
+    bytes
+        .chunks_exact(2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .collect()
+}
+
+fn read_u32s(bytes: &[u8]) -> Box<[u32]> {
+    bytes.chunks_exact(4).map(read_u32).collect()
+}
+
+fn read_u64s(bytes: &[u8]) -> Box<[u64]> {
+    bytes.chunks_exact(8).map(read_u64).collect()
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes.try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes.try_into().unwrap())
+}
+
+/// Writes an archived copy of each trie, and of the decomp, FCD and variable tables, under
+/// `archive/` in the output directory. With `verify`, reads each one back and checks that it
+/// holds the same table.
+pub fn map_archives(paths: &Paths, verify: bool) -> Result<()> {
+    for name in list_outputs(&paths.out_dir)? {
+        if name == "implicit" || name == "bundle" {
+            continue;
+        }
+
+        let path = paths.out(&name);
+        let archive_path = paths.out(&format!("archive/{name}"));
+        match TableKind::of(&name) {
+            TableKind::Trie => archive::<CollationTrieTable>(&path, &archive_path, verify)?,
+            TableKind::Decomp => archive::<DecompTable>(&path, &archive_path, verify)?,
+            TableKind::Fcd => archive::<FcdTable>(&path, &archive_path, verify)?,
+            TableKind::Variable => archive::<VariableTable>(&path, &archive_path, verify)?,
+        }
+    }
+
+    Ok(())
+}
+
+fn archive<T>(path: &Path, archive_path: &Path, verify: bool) -> Result<()>
+where
+    T: Archived + serde::de::DeserializeOwned + serde::Serialize,
+{
+    let table: T = read_table(path)?;
+    let bytes = table.to_archive();
+    write_output(archive_path, &bytes)?;
+
+    if verify {
+        let read_back = T::from_archive(&bytes).map_err(|e| Error::invalid(archive_path, e))?;
+        if postcard::to_allocvec(&read_back).ok() != postcard::to_allocvec(&table).ok() {
+            return Err(Error::invalid(
+                archive_path,
+                "doesn't match the postcard table",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<T>(name: &str)
+    where
+        T: Archived + serde::de::DeserializeOwned + serde::Serialize,
+    {
+        let table: T = read_table(&Paths::default().out(name)).unwrap();
+        let bytes = table.to_archive();
+
+        assert_eq!(bytes.len() % ALIGN, 0);
+        let read_back = T::from_archive(&bytes).unwrap();
+        assert_eq!(
+            postcard::to_allocvec(&read_back).unwrap(),
+            postcard::to_allocvec(&table).unwrap(),
+            "{name}"
+        );
+    }
+
+    #[test]
+    fn archives_round_trip() {
+        round_trip::<CollationTrieTable>("tailoring/sv");
+        round_trip::<DecompTable>("decomp");
+        round_trip::<FcdTable>("fcd");
+        round_trip::<VariableTable>("variable");
+
+        let fcd: FcdTable = read_table(&Paths::default().out("fcd")).unwrap();
+        let bytes = fcd.to_archive();
+        assert!(VariableTable::from_archive(&bytes).is_err());
+        assert!(FcdTable::from_archive(&bytes[..40]).is_err());
+    }
+
+    #[test]
+    fn corrupted_archives() {
+        let mut sv: CollationTrieTable = read_table(&Paths::default().out("tailoring/sv")).unwrap();
+        sv.prefixes = [PrefixMapping {
+            prefix: [0x30FC].into(),
+            code_points: [0x3099].into(),
+            weight_start: 0,
+            weight_len: 1,
+        }]
+        .into();
+        let bytes = sv.to_archive();
+        assert!(CollationTrieTable::from_archive(&bytes).is_ok());
+
+        let section = |i: usize| HEADER_LEN + i * DIRECTORY_ENTRY_LEN;
+        let offset = |i| usize::try_from(read_u64(&bytes[section(i)..section(i) + 8])).unwrap();
+
+        // A prefix mapping that reaches past the end of the code point pool
+        let mut corrupted = bytes.clone();
+        corrupted[offset(4)..offset(4) + 4].copy_from_slice(&1u32.to_le_bytes());
+        assert!(CollationTrieTable::from_archive(&corrupted).is_err());
+
+        // An edges section that ends partway through an edge
+        let mut corrupted = bytes;
+        let len = section(3) + 8;
+        let edges_len = read_u64(&corrupted[len..len + 8]) - 4;
+        corrupted[len..len + 8].copy_from_slice(&edges_len.to_le_bytes());
+        assert!(CollationTrieTable::from_archive(&corrupted).is_err());
+    }
+}
@@ -0,0 +1,291 @@
+#![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
+
+use crate::{
+    common::{CODE_POINT_COUNT, PAGE_SIZE},
+    diff::list_outputs,
+    error::Result,
+    paths::{Paths, read_table, write_table},
+    trie::{
+        CollationTrieTable, ContractionEdge, ContractionMeta, PrefixMapping, dedupe_entry_pages,
+        entry_len, entry_meta_index, entry_start, entry_tag, pack_entry,
+    },
+};
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+/// Page index value for a page that an overlay shares with the base
+pub const BASE_PAGE: u16 = u16::MAX;
+
+/// The CLDR root trie, with DUCET and every tailoring stored as overlays on it
+#[derive(Deserialize, Serialize)]
+pub struct TrieBundle {
+    pub base: CollationTrieTable,
+    pub overlays: Box<[TrieOverlay]>,
+}
+
+/// A trie stored as its differences from a base trie. Pages that match the base exactly are
+/// left out, and weight rows that the base already has are shared with it.
+#[derive(Deserialize, Serialize)]
+pub struct TrieOverlay {
+    /// As in the output directory, e.g. `ducet` or `tailoring/sv`
+    pub name: String,
+    /// `BASE_PAGE` for each page that is the same as in the base
+    pub page_index: Box<[u16]>,
+    pub entries: Box<[u64]>,
+    /// These are always complete, since they're small and their indexes are part of the entries
+    pub contraction_meta: Box<[ContractionMeta]>,
+    pub edges: Box<[ContractionEdge]>,
+    /// Rows that the base lacks. Weight starts past the end of the base's weights index into
+    /// this.
+    pub weights: Box<[u32]>,
+    /// As in the trie, and complete like the contraction metadata and edges
+    pub non_starters: Box<[bool]>,
+    pub edge_cccs: Box<[u8]>,
+    pub next_non_starters: Box<[bool]>,
+    pub prefixes: Box<[PrefixMapping]>,
+}
+
+impl TrieBundle {
+    #[must_use]
+    pub fn build(base: CollationTrieTable, tables: &[(String, CollationTrieTable)]) -> Self {
+        let overlays = tables
+            .iter()
+            .map(|(name, table)| TrieOverlay::build(&base, name, table))
+            .collect();
+
+        Self { base, overlays }
+    }
+
+    #[must_use]
+    pub fn overlay(&self, name: &str) -> Option<&TrieOverlay> {
+        self.overlays.iter().find(|overlay| overlay.name == name)
+    }
+}
+
+impl TrieOverlay {
+    #[must_use]
+    pub fn build(base: &CollationTrieTable, name: &str, table: &CollationTrieTable) -> Self {
+        let mut rows = OverlayRows::new(base);
+
+        let entries: Vec<u64> = (0..u32::try_from(CODE_POINT_COUNT).unwrap())
+            .map(|code_point| {
+                let entry = table.entry(code_point);
+                let start = rows.insert(table, entry_start(entry), entry_len(entry));
+                pack_entry(
+                    entry_tag(entry),
+                    start,
+                    entry_len(entry),
+                    entry_meta_index(entry),
+                )
+            })
+            .collect();
+
+        let edges = table
+            .edges
+            .iter()
+            .map(|edge| ContractionEdge {
+                weight_start: rows.insert(table, edge.weight_start, edge.weight_len),
+                ..edge.clone()
+            })
+            .collect();
+
+        let prefixes = table
+            .prefixes
+            .iter()
+            .map(|mapping| PrefixMapping {
+                weight_start: rows.insert(table, mapping.weight_start, mapping.weight_len),
+                ..mapping.clone()
+            })
+            .collect();
+
+        let mut page_index = Vec::with_capacity(CODE_POINT_COUNT / PAGE_SIZE);
+        let mut page_ids: FxHashMap<&[u64], u16> = FxHashMap::default();
+        let mut overlay_entries = Vec::new();
+
+        for (i, page) in entries.chunks_exact(PAGE_SIZE).enumerate() {
+            if page == base_page(base, i) {
+                page_index.push(BASE_PAGE);
+                continue;
+            }
+
+            let page_id = *page_ids.entry(page).or_insert_with(|| {
+                overlay_entries.extend_from_slice(page);
+                u16::try_from(overlay_entries.len() / PAGE_SIZE - 1).unwrap()
+            });
+            page_index.push(page_id);
+        }
+
+        Self {
+            name: name.to_string(),
+            page_index: page_index.into_boxed_slice(),
+            entries: overlay_entries.into_boxed_slice(),
+            contraction_meta: table.contraction_meta.clone(),
+            edges,
+            weights: rows.weights.into_boxed_slice(),
+            non_starters: table.non_starters.clone(),
+            edge_cccs: table.edge_cccs.clone(),
+            next_non_starters: table.next_non_starters.clone(),
+            prefixes,
+        }
+    }
+
+    /// Rebuilds the standalone trie
+    #[must_use]
+    pub fn to_table(&self, base: &CollationTrieTable) -> CollationTrieTable {
+        let mut entries = Vec::with_capacity(CODE_POINT_COUNT);
+        for (i, &page) in self.page_index.iter().enumerate() {
+            if page == BASE_PAGE {
+                entries.extend_from_slice(base_page(base, i));
+            } else {
+                let start = usize::from(page) * PAGE_SIZE;
+                entries.extend_from_slice(&self.entries[start..start + PAGE_SIZE]);
+            }
+        }
+        let (page_index, entries) = dedupe_entry_pages(&entries);
+
+        CollationTrieTable {
+            page_index,
+            entries,
+            contraction_meta: self.contraction_meta.clone(),
+            edges: self.edges.clone(),
+            weights: [base.weights.as_ref(), self.weights.as_ref()]
+                .concat()
+                .into(),
+            non_starters: self.non_starters.clone(),
+            edge_cccs: self.edge_cccs.clone(),
+            next_non_starters: self.next_non_starters.clone(),
+            prefixes: self.prefixes.clone(),
+        }
+    }
+}
+
+fn base_page(base: &CollationTrieTable, i: usize) -> &[u64] {
+    let start = usize::from(base.page_index[i]) * PAGE_SIZE;
+    &base.entries[start..start + PAGE_SIZE]
+}
+
+// Where each row of weights will be found, whether in the base or in the overlay
+struct OverlayRows<'a> {
+    base_len: u32,
+    starts: FxHashMap<&'a [u32], u32>,
+    weights: Vec<u32>,
+}
+
+impl<'a> OverlayRows<'a> {
+    fn new(base: &'a CollationTrieTable) -> Self {
+        let mut starts = FxHashMap::default();
+
+        let entries = base.entries.iter().map(|&e| (entry_start(e), entry_len(e)));
+        let edges = base.edges.iter().map(|e| (e.weight_start, e.weight_len));
+        let prefixes = base.prefixes.iter().map(|p| (p.weight_start, p.weight_len));
+        let rows = entries.chain(edges).chain(prefixes);
+        for (start, len) in rows.filter(|&(_, len)| len > 0) {
+            let row = &base.weights[row_range(start, len)];
+            starts.entry(row).or_insert(start);
+        }
+
+        Self {
+            base_len: u32::try_from(base.weights.len()).unwrap(),
+            starts,
+            weights: Vec::new(),
+        }
+    }
+
+    fn insert(&mut self, table: &'a CollationTrieTable, start: u32, len: u16) -> u32 {
+        if len == 0 {
+            return start;
+        }
+
+        let row = &table.weights[row_range(start, len)];
+        *self.starts.entry(row).or_insert_with(|| {
+            let start = self.base_len + u32::try_from(self.weights.len()).unwrap();
+            self.weights.extend_from_slice(row);
+            start
+        })
+    }
+}
+
+fn row_range(start: u32, len: u16) -> std::ops::Range<usize> {
+    let start = usize::try_from(start).unwrap();
+    start..start + usize::from(len)
+}
+
+/// Bundles the DUCET trie and every tailoring in the output directory onto the CLDR root trie
+pub fn map_bundle(paths: &Paths) -> Result<TrieBundle> {
+    let bundle = TrieBundle::build(
+        read_table(&paths.out("cldr_root"))?,
+        &bundled_tables(paths)?,
+    );
+    write_table(&paths.out("bundle"), &bundle)?;
+    Ok(bundle)
+}
+
+/// Names of the overlays whose rebuilt tries don't have the same mappings as the originals
+pub fn verify_bundle(paths: &Paths, bundle: &TrieBundle) -> Result<Vec<String>> {
+    let mut mismatches = Vec::new();
+
+    for (name, table) in bundled_tables(paths)? {
+        let rebuilt = bundle
+            .overlay(&name)
+            .map(|overlay| overlay.to_table(&bundle.base));
+        let differs = |rebuilt: CollationTrieTable| {
+            rebuilt.mappings() != table.mappings()
+                || !rebuilt.prefix_mappings().eq(table.prefix_mappings())
+        };
+        if rebuilt.is_none_or(differs) {
+            mismatches.push(name);
+        }
+    }
+
+    Ok(mismatches)
+}
+
+fn bundled_tables(paths: &Paths) -> Result<Vec<(String, CollationTrieTable)>> {
+    list_outputs(&paths.out_dir)?
+        .into_iter()
+        .filter(|name| name == "ducet" || name.starts_with("tailoring/"))
+        .map(|name| {
+            let table = read_table(&paths.out(&name))?;
+            Ok((name, table))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlays_round_trip() {
+        let paths = Paths::default();
+        let base: CollationTrieTable = read_table(&paths.out("cldr_root")).unwrap();
+        let tables: Vec<(String, CollationTrieTable)> = ["ducet", "tailoring/sv"]
+            .into_iter()
+            .map(|name| (name.to_string(), read_table(&paths.out(name)).unwrap()))
+            .collect();
+
+        let bundle = TrieBundle::build(base, &tables);
+        let bytes = postcard::to_allocvec(&bundle).unwrap();
+        let bundle: TrieBundle = postcard::from_bytes(&bytes).unwrap();
+
+        // Smaller than the standalone tries, even with the base included
+        let standalone: usize = tables
+            .iter()
+            .map(|(_, table)| postcard::to_allocvec(table).unwrap().len())
+            .sum();
+        assert!(bytes.len() < standalone);
+
+        for (name, table) in &tables {
+            let overlay = bundle.overlay(name).unwrap();
+            // At least 95% of the pages are the base's
+            let shared = (overlay.page_index.iter())
+                .filter(|&&page| page == BASE_PAGE)
+                .count();
+            assert!(shared * 20 >= overlay.page_index.len() * 19, "{name}");
+            assert!(overlay.entries.len() < table.entries.len(), "{name}");
+            assert!(overlay.weights.len() < table.weights.len(), "{name}");
+            assert!(overlay.to_table(&bundle.base).mappings() == table.mappings());
+        }
+        assert!(bundle.overlay("tailoring/da").is_none());
+    }
+}
@@ -0,0 +1,408 @@
+use feruca_mapper::{DataVersion, Paths, VariableWeighting};
+
+pub const USAGE: &str = "\
+Usage: feruca-mapper [COMMAND] [OPTIONS]
+
+Commands:
+  all                      Generate every table, including the legacy layout
+                           and FractionalUCA tables where the data version has
+                           them (default)
+  decomp                   Canonical decompositions
+  fcd                      FCD values (reads the decomp output)
+  variable                 Variable-weight code points
+  implicit                 Implicit primaries for Han, Tangut, Nushu, Khitan and
+                           unassigned code points
+  low                      Low code point mappings (DUCET and CLDR)
+  trie [--tailoring NAME]  Collation tries; NAME is one of ducet, cldr,
+                           custom (every tailoring in DIR/tailorings.json),
+                           locales (the CLDR rules in DIR/collation), all, or
+                           the name of a tailoring in DIR/tailorings.json
+  bundle                   DUCET and every tailoring in the output directory, as
+                           overlays on the CLDR root trie in a single file
+  archive                  Copies of the tries and the decomp, FCD and variable
+                           tables in a fixed little-endian layout that feruca can
+                           use without deserializing, under archive/ in the
+                           output directory
+  legacy                   Map-based tables for releases before CLDR 46.1
+  fractional               CLDR root trie from FractionalUCA_SHORT.txt, checked
+                           against the adjusted allkeys_CLDR.txt weights
+  reorder CODE...          CLDR trie with scripts and groups reordered as by
+                           [reorder ...], e.g. `reorder Cyrl` or
+                           `reorder Grek Latn digit`; needs FractionalUCA_SHORT.txt
+  rules NAME...            CLDR trie tailored by the LDML rules in
+                           DIR/collation/NAME.txt, where DIR is the data directory
+  conformance              Check the generated DUCET and CLDR tables against any
+                           DIR/CollationTest_*.txt files
+  key [--table NAME] [--non-ignorable] TEXT...
+                           Print the sort key of each TEXT, computed from the
+                           generated tables; NAME is ducet, cldr (default) or
+                           the name of a trie under tailoring/
+  dump NAME...             Print the mappings of each trie in an allkeys-like
+                           format; NAME is ducet, cldr or the name of a trie
+                           under tailoring/
+  stats                    Report the page, entry, contraction and pool counts,
+                           dedupe savings and file size of every table in the
+                           output directory
+  diff [--json] OLD NEW    Report what changed per code point between two sets
+                           of generated tables; OLD and NEW are data version
+                           names or output directories
+
+Options:
+  --data-version NAME      One of 14, 15, cldr-43, cldr-44, cldr-46_1; sets the
+                           directories below [default: cldr-46_1]
+  --data-dir DIR           Unicode/CLDR input files [default: unicode-data/NAME]
+  --out-dir DIR            Postcard output [default: bincode/NAME, or
+                           bincode/NAME/postcard for releases with a legacy layout]
+  --json-dir DIR           JSON debugging output [default: json/NAME]
+  --legacy-dir DIR         Legacy output [default: bincode/NAME]
+  --verify                 Read back each root, custom and rules-based trie
+                           after writing it, and check it against its mappings
+  -h, --help               Print this message";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    All,
+    Decomp,
+    Fcd,
+    Variable,
+    Implicit,
+    Low,
+    Trie(TrieTarget),
+    Bundle,
+    Archive,
+    Legacy,
+    Fractional,
+    /// Script codes and group names, in the order given
+    Reorder(Vec<String>),
+    /// Names of rule files, without `.txt`
+    Rules(Vec<String>),
+    Conformance,
+    Key(KeyQuery),
+    /// Trie names, as for `--table`
+    Dump(Vec<String>),
+    Stats,
+    Diff(DiffQuery),
+    Help,
+}
+
+/// Strings to get sort keys for, and the tables to use
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyQuery {
+    pub table: String,
+    pub weighting: VariableWeighting,
+    pub texts: Vec<String>,
+}
+
+/// Two sets of outputs to compare, each a data version name or an output directory
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiffQuery {
+    pub outputs: Vec<String>,
+    pub json: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TrieTarget {
+    All,
+    Ducet,
+    Cldr,
+    /// Every tailoring declared in `tailorings.json`
+    Custom,
+    /// One of the tailorings in `tailorings.json`, by name
+    Named(String),
+    Locales,
+}
+
+impl TrieTarget {
+    // Names of custom tailorings are checked against the data directory when the tries are built
+    fn parse(name: &str) -> Self {
+        match name {
+            "all" => Self::All,
+            "ducet" => Self::Ducet,
+            "cldr" => Self::Cldr,
+            "custom" => Self::Custom,
+            "locales" => Self::Locales,
+            name => Self::Named(name.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Args {
+    pub command: Command,
+    pub paths: Paths,
+    pub verify: bool,
+}
+
+pub fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut command = None;
+    let mut tailoring = None;
+    let mut version = DataVersion::default();
+    let mut data_dir = None;
+    let mut out_dir = None;
+    let mut json_dir = None;
+    let mut legacy_dir = None;
+    let mut verify = false;
+    let mut table = None;
+    let mut weighting = None;
+    let mut json = false;
+
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+
+        let mut value = || {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{flag} requires a value"))
+        };
+
+        match flag {
+            "--data-version" => version = value()?.parse()?,
+            "--data-dir" => data_dir = Some(value()?.into()),
+            "--out-dir" => out_dir = Some(value()?.into()),
+            "--json-dir" => json_dir = Some(value()?.into()),
+            "--legacy-dir" => legacy_dir = Some(value()?.into()),
+            "--tailoring" | "-t" => tailoring = Some(TrieTarget::parse(&value()?)),
+            "--verify" => verify = true,
+            "--table" => table = Some(value()?),
+            "--non-ignorable" => weighting = Some(VariableWeighting::NonIgnorable),
+            "--json" => json = true,
+            "-h" | "--help" => command = Some(Command::Help),
+            _ if flag.starts_with('-') => return Err(format!("unknown option: {flag}")),
+            _ if let Some(
+                Command::Reorder(names)
+                | Command::Rules(names)
+                | Command::Dump(names)
+                | Command::Key(KeyQuery { texts: names, .. })
+                | Command::Diff(DiffQuery { outputs: names, .. }),
+            ) = &mut command =>
+            {
+                names.push(arg);
+            }
+            _ if command.is_some() => return Err(format!("unexpected argument: {arg}")),
+            _ => command = Some(parse_command(&arg)?),
+        }
+    }
+
+    let command = match (command.unwrap_or(Command::All), table, weighting) {
+        (Command::Key(query), _, _) if query.texts.is_empty() => {
+            return Err("key requires at least one string".to_string());
+        }
+        (Command::Key(query), table, weighting) => Command::Key(KeyQuery {
+            table: table.unwrap_or(query.table),
+            weighting: weighting.unwrap_or(query.weighting),
+            texts: query.texts,
+        }),
+        (_, Some(_), _) => return Err("--table is only valid with key".to_string()),
+        (_, _, Some(_)) => return Err("--non-ignorable is only valid with key".to_string()),
+        (command, None, None) => command,
+    };
+
+    let command = match (command, json) {
+        (Command::Diff(query), _) if query.outputs.len() != 2 => {
+            return Err("diff requires exactly two sets of outputs".to_string());
+        }
+        (Command::Diff(query), json) => Command::Diff(DiffQuery { json, ..query }),
+        (_, true) => return Err("--json is only valid with diff".to_string()),
+        (command, false) => command,
+    };
+
+    let command = match (command, tailoring) {
+        (Command::Reorder(codes), _) if codes.is_empty() => {
+            return Err("reorder requires at least one script code".to_string());
+        }
+        (Command::Rules(names), _) if names.is_empty() => {
+            return Err("rules requires at least one rule file name".to_string());
+        }
+        (Command::Dump(names), _) if names.is_empty() => {
+            return Err("dump requires at least one trie name".to_string());
+        }
+        (Command::Trie(_), Some(target)) => Command::Trie(target),
+        (_, Some(_)) => return Err("--tailoring is only valid with trie".to_string()),
+        (command, None) => command,
+    };
+
+    let mut paths = Paths::for_version(version);
+    paths.data_dir = data_dir.unwrap_or(paths.data_dir);
+    paths.out_dir = out_dir.unwrap_or(paths.out_dir);
+    paths.json_dir = json_dir.unwrap_or(paths.json_dir);
+    paths.legacy_dir = legacy_dir.unwrap_or(paths.legacy_dir);
+
+    Ok(Args {
+        command,
+        paths,
+        verify,
+    })
+}
+
+fn parse_command(name: &str) -> Result<Command, String> {
+    match name {
+        "all" => Ok(Command::All),
+        "decomp" => Ok(Command::Decomp),
+        "fcd" => Ok(Command::Fcd),
+        "variable" => Ok(Command::Variable),
+        "implicit" => Ok(Command::Implicit),
+        "low" => Ok(Command::Low),
+        "trie" => Ok(Command::Trie(TrieTarget::All)),
+        "bundle" => Ok(Command::Bundle),
+        "archive" => Ok(Command::Archive),
+        "legacy" => Ok(Command::Legacy),
+        "fractional" => Ok(Command::Fractional),
+        "reorder" => Ok(Command::Reorder(Vec::new())),
+        "rules" => Ok(Command::Rules(Vec::new())),
+        "conformance" => Ok(Command::Conformance),
+        "key" => Ok(Command::Key(KeyQuery {
+            table: "cldr".to_string(),
+            weighting: VariableWeighting::default(),
+            texts: Vec::new(),
+        })),
+        "dump" => Ok(Command::Dump(Vec::new())),
+        "stats" => Ok(Command::Stats),
+        "diff" => Ok(Command::Diff(DiffQuery {
+            outputs: Vec::new(),
+            json: false,
+        })),
+        "help" => Ok(Command::Help),
+        _ => Err(format!("unknown command: {name}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(ToString::to_string)).map(|args| args.command)
+    }
+
+    #[test]
+    fn defaults_to_all() {
+        assert_eq!(parse(&[]), Ok(Command::All));
+    }
+
+    #[test]
+    fn trie_tailoring() {
+        assert_eq!(parse(&["trie"]), Ok(Command::Trie(TrieTarget::All)));
+        assert_eq!(
+            parse(&["trie", "--tailoring", "cldr"]),
+            Ok(Command::Trie(TrieTarget::Cldr))
+        );
+        assert_eq!(
+            parse(&["trie", "--tailoring=custom"]),
+            Ok(Command::Trie(TrieTarget::Custom))
+        );
+        assert_eq!(
+            parse(&["trie", "--tailoring=arabic_interleaved"]),
+            Ok(Command::Trie(TrieTarget::Named(
+                "arabic_interleaved".to_string()
+            )))
+        );
+        assert_eq!(
+            parse(&["trie", "-t", "locales"]),
+            Ok(Command::Trie(TrieTarget::Locales))
+        );
+        assert!(parse(&["trie", "--tailoring"]).is_err());
+        assert!(parse(&["fcd", "--tailoring", "cldr"]).is_err());
+    }
+
+    #[test]
+    fn directories() {
+        let args = parse_args(
+            ["--data-dir", "in", "fcd", "--out-dir=out"]
+                .into_iter()
+                .map(ToString::to_string),
+        )
+        .unwrap();
+
+        assert_eq!(args.command, Command::Fcd);
+        assert_eq!(args.paths.data_dir, std::path::Path::new("in"));
+        assert_eq!(args.paths.out_dir, std::path::Path::new("out"));
+        assert_eq!(args.paths.json_dir, Paths::default().json_dir);
+        assert!(!args.verify);
+        assert!(
+            parse_args(["trie", "--verify"].into_iter().map(ToString::to_string))
+                .unwrap()
+                .verify
+        );
+    }
+
+    #[test]
+    fn data_version() {
+        let args = parse_args(
+            ["legacy", "--data-version", "cldr-44"]
+                .into_iter()
+                .map(ToString::to_string),
+        )
+        .unwrap();
+
+        assert_eq!(args.command, Command::Legacy);
+        assert_eq!(args.paths, Paths::for_version(DataVersion::Cldr44));
+        assert!(parse(&["--data-version", "13"]).is_err());
+    }
+
+    #[test]
+    fn reorder_codes() {
+        assert_eq!(
+            parse(&["reorder", "Grek", "--data-version", "cldr-46_1", "Latn"]),
+            Ok(Command::Reorder(vec![
+                "Grek".to_string(),
+                "Latn".to_string()
+            ]))
+        );
+        assert!(parse(&["reorder"]).is_err());
+        assert_eq!(
+            parse(&["rules", "sv", "de-phonebook"]),
+            Ok(Command::Rules(vec![
+                "sv".to_string(),
+                "de-phonebook".to_string()
+            ]))
+        );
+        assert!(parse(&["rules"]).is_err());
+        assert_eq!(
+            parse(&["dump", "cldr", "arabic_interleaved"]),
+            Ok(Command::Dump(vec![
+                "cldr".to_string(),
+                "arabic_interleaved".to_string()
+            ]))
+        );
+        assert!(parse(&["dump"]).is_err());
+    }
+
+    #[test]
+    fn key_query() {
+        assert_eq!(
+            parse(&["key", "--table", "sv", "å", "--non-ignorable", "z"]),
+            Ok(Command::Key(KeyQuery {
+                table: "sv".to_string(),
+                weighting: VariableWeighting::NonIgnorable,
+                texts: vec!["å".to_string(), "z".to_string()],
+            }))
+        );
+        assert!(parse(&["key"]).is_err());
+        assert!(parse(&["trie", "--table", "sv"]).is_err());
+    }
+
+    #[test]
+    fn diff_query() {
+        assert_eq!(
+            parse(&["diff", "cldr-44", "--json", "bincode/cldr-46_1"]),
+            Ok(Command::Diff(DiffQuery {
+                outputs: vec!["cldr-44".to_string(), "bincode/cldr-46_1".to_string()],
+                json: true,
+            }))
+        );
+        assert!(parse(&["diff", "cldr-44"]).is_err());
+        assert!(parse(&["diff", "14", "15", "cldr-43"]).is_err());
+        assert!(parse(&["fcd", "--json"]).is_err());
+    }
+
+    #[test]
+    fn rejects_extra_arguments() {
+        assert!(parse(&["fcd", "extra"]).is_err());
+        assert!(parse(&["frobnicate"]).is_err());
+        assert!(parse(&["--frobnicate"]).is_err());
+    }
+}
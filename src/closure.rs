@@ -0,0 +1,300 @@
+#![allow(clippy::missing_panics_doc)]
+
+use crate::{common::CODE_POINT_COUNT, normalization::DecompTable};
+use rustc_hash::FxHashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    hash::BuildHasher,
+};
+use unicode_canonical_combining_class::get_canonical_combining_class_u32 as get_ccc;
+
+/// Adds the mappings that canonical equivalence implies, so that text maps the same way whatever
+/// its normalization
+///
+/// For each contraction, its NFD (decomposed and canonically ordered) form maps the same, if that
+/// differs, and so does the NFD form of each of the `changed` singles (given as one code point)
+/// that has a canonical decomposition, unless its parts already give the same weights. Each other
+/// code point with a canonical decomposition gets the weights of the decomposition wherever they
+/// involve a contraction or a changed mapping, overriding what it had; and a contraction that
+/// continues past a code point's decomposition gets a copy starting with the code point itself.
+/// Mappings that exist already are otherwise left alone, as are changed ones.
+pub fn canonical_closure<S1: BuildHasher, S2: BuildHasher, S3: BuildHasher>(
+    decomp: &DecompTable,
+    singles: &mut HashMap<u32, Box<[u32]>, S1>,
+    multis: &mut HashMap<Box<[u32]>, Box<[u32]>, S2>,
+    changed: &HashSet<Box<[u32]>, S3>,
+) {
+    let nfd = |code_points: &[u32]| -> Box<[u32]> {
+        let mut nfd = Vec::with_capacity(code_points.len());
+        for &cp in code_points {
+            match decomp.get(cp) {
+                Some(decomposed) => nfd.extend_from_slice(decomposed),
+                None => nfd.push(cp),
+            }
+        }
+        canonical_order(&mut nfd);
+        nfd.into()
+    };
+
+    let equivalents: Vec<_> = multis
+        .iter()
+        .map(|(code_points, weights)| (nfd(code_points), weights.clone()))
+        .filter(|(code_points, _)| !multis.contains_key(code_points))
+        .collect();
+    multis.extend(equivalents);
+
+    // Otherwise decomposed input would fall back to the weights of its parts, where those differ
+    let decompositions: Vec<_> = {
+        let (by_first, longest) = index_contractions(multis);
+        let elements = Elements {
+            singles,
+            multis,
+            by_first: &by_first,
+            longest,
+        };
+
+        (singles.iter())
+            .filter(|&(&code_point, _)| {
+                decomp.get(code_point).is_some() && changed.contains([code_point].as_slice())
+            })
+            .map(|(&code_point, weights)| (nfd(&[code_point]), weights.clone()))
+            .filter(|(code_points, weights)| {
+                code_points.len() > 1
+                    && !changed.contains(code_points)
+                    && (elements.of(code_points)).is_none_or(|parts| parts.weights != *weights)
+            })
+            .collect()
+    };
+    multis.extend(decompositions);
+
+    let (by_first, longest) = index_contractions(multis);
+
+    let mut new_singles = Vec::new();
+    let mut new_multis = Vec::new();
+    for code_point in 0..u32::try_from(CODE_POINT_COUNT).unwrap() {
+        if decomp.get(code_point).is_none() {
+            continue;
+        }
+        let tailored = changed.contains([code_point].as_slice());
+        let decomposed = nfd(&[code_point]);
+
+        let elements = Elements {
+            singles,
+            multis,
+            by_first: &by_first,
+            longest,
+        };
+        if !tailored
+            && let Some(expansion) = elements.of(&decomposed)
+            && (expansion.used.iter()).any(|key| key.len() > 1 || changed.contains(*key))
+            && singles.get(&code_point) != Some(&expansion.weights)
+        {
+            new_singles.push((code_point, expansion.weights));
+        }
+
+        for contraction in by_first.get(&decomposed[0]).into_iter().flatten() {
+            if contraction.len() > decomposed.len() && contraction.starts_with(&decomposed) {
+                let mut composed = vec![code_point];
+                composed.extend_from_slice(&contraction[decomposed.len()..]);
+                if !multis.contains_key(composed.as_slice()) {
+                    new_multis.push((composed.into(), multis[*contraction].clone()));
+                }
+            }
+        }
+    }
+
+    singles.extend(new_singles);
+    multis.extend(new_multis);
+}
+
+// The contractions by first code point, and the length of the longest
+fn index_contractions<S: BuildHasher>(
+    multis: &HashMap<Box<[u32]>, Box<[u32]>, S>,
+) -> (FxHashMap<u32, Vec<&[u32]>>, usize) {
+    let mut by_first: FxHashMap<u32, Vec<&[u32]>> = FxHashMap::default();
+    for code_points in multis.keys() {
+        by_first
+            .entry(code_points[0])
+            .or_default()
+            .push(code_points);
+    }
+    let longest = multis.keys().map(|k| k.len()).max().unwrap_or(0);
+
+    (by_first, longest)
+}
+
+// Stable sort of each run of non-starters by combining class
+fn canonical_order(code_points: &mut [u32]) {
+    let mut start = 0;
+    while start < code_points.len() {
+        let len = code_points[start..]
+            .iter()
+            .take_while(|&&cp| get_ccc(cp) as u8 != 0)
+            .count();
+        code_points[start..start + len].sort_by_key(|&cp| get_ccc(cp) as u8);
+        start += len.max(1);
+    }
+}
+
+struct Expansion<'a> {
+    weights: Box<[u32]>,
+    // Keys of the mappings that the weights came from
+    used: Vec<&'a [u32]>,
+}
+
+struct Elements<'a, S1: BuildHasher, S2: BuildHasher> {
+    singles: &'a HashMap<u32, Box<[u32]>, S1>,
+    multis: &'a HashMap<Box<[u32]>, Box<[u32]>, S2>,
+    by_first: &'a FxHashMap<u32, Vec<&'a [u32]>>,
+    longest: usize,
+}
+
+impl<'a, S1: BuildHasher, S2: BuildHasher> Elements<'a, S1, S2> {
+    // The weights of an NFD string, found as feruca would: the longest contraction at each
+    // position, extended over unblocked non-starters where that makes a longer one. None if a code
+    // point has no mapping, i.e. it has implicit weights.
+    fn of(&self, nfd: &[u32]) -> Option<Expansion<'a>> {
+        let mut code_points = nfd.to_vec();
+        let mut weights = Vec::new();
+        let mut used = Vec::new();
+        let mut i = 0;
+
+        while i < code_points.len() {
+            let rest = &code_points[i..];
+            let contraction = (2..=self.longest.min(rest.len()))
+                .rev()
+                .find_map(|len| self.multis.get_key_value(&rest[..len]));
+            let (mut key, mut row, consumed) = if let Some((key, row)) = contraction {
+                (key.as_ref(), row, key.len())
+            } else {
+                let (key, row) = self.singles.get_key_value(&rest[0])?;
+                (std::slice::from_ref(key), row, 1)
+            };
+
+            if self.by_first.contains_key(&code_points[i]) {
+                let mut matched = key.to_vec();
+                let mut j = i + consumed;
+                let mut last_skipped = 0;
+
+                while let Some(&cp) = code_points.get(j) {
+                    let ccc = get_ccc(cp) as u8;
+                    if ccc == 0 {
+                        break;
+                    }
+
+                    matched.push(cp);
+                    match self.multis.get_key_value(matched.as_slice()) {
+                        Some((k, r)) if last_skipped < ccc => {
+                            (key, row) = (k.as_ref(), r);
+                            code_points.remove(j);
+                        }
+                        _ => {
+                            matched.pop();
+                            last_skipped = ccc;
+                            j += 1;
+                        }
+                    }
+                }
+            }
+
+            weights.extend_from_slice(row);
+            used.push(key);
+            i += consumed;
+        }
+
+        Some(Expansion {
+            weights: weights.into(),
+            used,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collation::{collect_multis, collect_singles},
+        normalization::{build_decomp_table, collect_decomps},
+        paths::Paths,
+    };
+    use feruca::Tailoring;
+    use rustc_hash::FxHashSet;
+
+    #[test]
+    fn closes_contractions() {
+        // Й is И with a breve; ѝ is и with a grave; å is a with a ring
+        let decomps: FxHashMap<u32, Box<[u32]>> = [
+            (0x0419, [0x0418, 0x0306].into()),
+            (0x045D, [0x0438, 0x0300].into()),
+            (0x00E5, [0x0061, 0x030A].into()),
+            (0x00E0, [0x0061, 0x0300].into()),
+        ]
+        .into_iter()
+        .collect();
+        let decomp = build_decomp_table(&decomps);
+
+        let mut singles: FxHashMap<u32, Box<[u32]>> = [
+            (0x0418, [1].into()),
+            (0x0306, [2].into()),
+            (0x0419, [9].into()),
+            (0x0438, [3].into()),
+            (0x0300, [4].into()),
+            (0x0323, [5].into()),
+            (0x045D, [3, 4].into()),
+            (0x0061, [12].into()),
+            (0x030A, [13].into()),
+        ]
+        .into_iter()
+        .collect();
+        let mut multis: FxHashMap<Box<[u32]>, Box<[u32]>> = [
+            ([0x0418, 0x0306].into(), [6].into()),
+            ([0x0418, 0x0306, 0x0306].into(), [7].into()),
+            // Not in canonical order: dot below comes before grave
+            ([0x0438, 0x0300, 0x0323].into(), [8].into()),
+        ]
+        .into_iter()
+        .collect();
+        let mut changed = FxHashSet::default();
+        changed.insert(Box::from([0x0438]));
+        singles.insert(0x0438, [10].into());
+        // A tailored precomposed letter keeps its own weights, even though its base letter changed,
+        // and its decomposition maps the same
+        changed.insert(Box::from([0x0061]));
+        changed.insert(Box::from([0x00E5]));
+        singles.insert(0x00E5, [11].into());
+        // à changed along with a, and its decomposition already maps the same
+        changed.insert(Box::from([0x00E0]));
+        singles.insert(0x00E0, [12, 4].into());
+
+        canonical_closure(&decomp, &mut singles, &mut multis, &changed);
+
+        // Precomposed forms follow the contraction and the tailored base letter
+        assert_eq!(*singles[&0x0419], [6]);
+        assert_eq!(*singles[&0x045D], [10, 4]);
+        assert_eq!(*singles[&0x00E5], [11]);
+        assert_eq!(*multis[[0x0419, 0x0306].as_slice()], [7]);
+        assert_eq!(*multis[[0x0438, 0x0323, 0x0300].as_slice()], [8]);
+        assert_eq!(*multis[[0x0438, 0x0300, 0x0323].as_slice()], [8]);
+        assert_eq!(*multis[[0x045D, 0x0323].as_slice()], [8]);
+        assert_eq!(*multis[[0x0061, 0x030A].as_slice()], [11]);
+        assert!(!multis.contains_key([0x0061, 0x0300].as_slice()));
+        assert_eq!(multis.len(), 7);
+    }
+
+    // The root tables come out of allkeys closed already, so only tailorings can add anything
+    #[test]
+    fn root_is_closed() {
+        let paths = Paths::default();
+        let decomp = build_decomp_table(&collect_decomps(&paths).unwrap());
+
+        let root_singles = collect_singles(&paths, Tailoring::default()).unwrap();
+        let root_multis = collect_multis(&paths, Tailoring::default()).unwrap();
+        let (mut singles, mut multis) = (root_singles.clone(), root_multis.clone());
+        let changed: FxHashSet<Box<[u32]>> = FxHashSet::default();
+
+        canonical_closure(&decomp, &mut singles, &mut multis, &changed);
+
+        assert_eq!(singles, root_singles);
+        assert_eq!(multis, root_multis);
+    }
+}
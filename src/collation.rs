@@ -1,15 +1,17 @@
-#![allow(clippy::missing_panics_doc, clippy::regex_creation_in_loops)]
+#![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
 
-use crate::regex;
+use crate::{
+    allkeys::{CollationElement, CollationElementTable},
+    error::{Result, SourceLine},
+    paths::{Paths, write_json},
+};
 use feruca::Tailoring;
 use rustc_hash::FxHashMap;
-use std::{hash::Hash, sync::LazyLock};
-
-pub static KEYS_DUCET: LazyLock<String> =
-    LazyLock::new(|| std::fs::read_to_string("unicode-data/cldr-46_1/allkeys.txt").unwrap());
-
-pub static KEYS_CLDR: LazyLock<String> =
-    LazyLock::new(|| std::fs::read_to_string("unicode-data/cldr-46_1/allkeys_CLDR.txt").unwrap());
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    hash::{BuildHasher, Hash},
+};
 
 // This adjustment affects only the low and singles maps
 pub const BUMP: u16 = 1;
@@ -23,189 +25,159 @@ const SHIFT_END: u16 = 0x72B6; // Large gap above this that we can use
 const SEC_MAX: u16 = 0x126; // Largest secondary weight that is actually used
 const TER_MAX: u16 = 0x1E; // Largest tertiary weight that is actually used
 
-pub fn map_low(keys: Tailoring) {
+pub fn map_low(paths: &Paths, keys: Tailoring) -> Result<()> {
     let cldr = keys != Tailoring::Ducet;
+    let map = collect_low(paths, keys)?;
 
-    let data = if cldr { &KEYS_CLDR } else { &KEYS_DUCET };
+    // Since we have 181 code points with values in the range 0..183, we can put the associated
+    // collation weights into an array such that the index is the code point value.
+    let mut arr = [0u32; 183];
+    for kv in &map {
+        arr[*kv.0 as usize] = *kv.1;
+    }
 
-    let re_set_of_weights = regex!(r"[*.\dA-F]{15}");
-    let re_individual_weight = regex!(r"[\dA-F]{4}");
+    for (i, &v) in arr.iter().enumerate() {
+        let map_val = map.get(&u32::try_from(i).unwrap()).unwrap_or(&0);
+        assert_eq!(v, *map_val);
+    }
 
-    let mut map: FxHashMap<u32, u32> = FxHashMap::default();
+    // Write to JSON only in this case; we'll copy-paste the values into feruca source code
+    let path_out = if cldr {
+        paths.json("low_cldr.json")
+    } else {
+        paths.json("low.json")
+    };
 
-    for line in data.lines() {
-        if line.is_empty() || line.starts_with('@') || line.starts_with('#') {
-            continue;
-        }
+    write_json(&path_out, &arr.as_slice())
+}
 
-        let mut split_at_semicolon = line.split(';');
-        let left_of_semicolon = split_at_semicolon.next().unwrap();
-        let right_of_semicolon = split_at_semicolon.next().unwrap();
-        let left_of_hash = right_of_semicolon.split('#').next().unwrap();
+pub fn collect_low(paths: &Paths, keys: Tailoring) -> Result<FxHashMap<u32, u32>> {
+    let adjust = keys != Tailoring::Ducet && paths.version.adjusts_cldr_primaries();
+    let table = CollationElementTable::read(&paths.allkeys(keys))?;
 
-        let re_key = regex!(r"[\dA-F]{4,5}");
+    let mut map: FxHashMap<u32, u32> = FxHashMap::default();
 
-        let first_cp = re_key.find(left_of_semicolon).unwrap().as_str();
-        let cp = u32::from_str_radix(first_cp, 16).unwrap();
+    for entry in &table.entries {
+        let cp = entry.code_points[0];
 
         // Skip capital and lowercase L; it's problematic
         if cp > 0xB6 || cp == 0x4C || cp == 0x6C {
             continue;
         }
 
-        let first_set = re_set_of_weights.find(left_of_hash).unwrap().as_str();
-
-        let variable = first_set.starts_with('*');
+        let packed = pack_element(table.source(entry), entry.elements[0], adjust, true)?;
+        map.insert(cp, packed);
+    }
 
-        let mut weights = re_individual_weight.find_iter(first_set);
+    Ok(map)
+}
 
-        let mut primary = u16::from_str_radix(weights.next().unwrap().as_str(), 16).unwrap();
-        if cldr && (BUMP_START..=BUMP_END).contains(&primary) {
-            primary += BUMP;
-        }
-        if cldr && (SHIFT_START..=SHIFT_END).contains(&primary) {
-            primary += SHIFT;
+/// The first collation element of each low code point, indexed by code point, as in `map_low`
+///
+/// Code points that start a contraction are left at zero, as L and l are in every root table,
+/// since feruca has to find those in the trie.
+#[must_use]
+pub fn low_from_mappings<S1: BuildHasher, S2: BuildHasher>(
+    singles: &HashMap<u32, Box<[u32]>, S1>,
+    multis: &HashMap<Box<[u32]>, Box<[u32]>, S2>,
+) -> [u32; 183] {
+    let mut low = [0u32; 183];
+    for (&code_point, weights) in singles {
+        if let Some(slot) = low.get_mut(usize::try_from(code_point).unwrap()) {
+            *slot = weights[0];
         }
-
-        let secondary = u16::from_str_radix(weights.next().unwrap().as_str(), 16).unwrap();
-        assert!(secondary <= SEC_MAX);
-
-        let tertiary = u16::from_str_radix(weights.next().unwrap().as_str(), 16).unwrap();
-        assert!(tertiary <= TER_MAX);
-
-        let packed = pack_weights(variable, primary, secondary, tertiary);
-
-        map.insert(cp, packed);
     }
 
-    // Since we have 181 code points with values in the range 0..183, we can put the associated
-    // collation weights into an array such that the index is the code point value.
-    let mut arr = [0u32; 183];
-    for kv in &map {
-        arr[*kv.0 as usize] = *kv.1;
+    // Capital and lowercase L are left out here too
+    low[0x4C] = 0;
+    low[0x6C] = 0;
+    for code_points in multis.keys() {
+        if let Some(slot) = low.get_mut(usize::try_from(code_points[0]).unwrap()) {
+            *slot = 0;
+        }
     }
 
-    for (i, &v) in arr.iter().enumerate() {
-        let map_val = map.get(&u32::try_from(i).unwrap()).unwrap_or(&0);
-        assert_eq!(v, *map_val);
-    }
+    low
+}
 
-    // Write to JSON only in this case; we'll copy-paste the values into feruca source code
-    let path_out = if cldr {
-        "json/cldr-46_1/low_cldr.json"
-    } else {
-        "json/cldr-46_1/low.json"
-    };
+/// Weights of contractions, keyed by their code points
+pub type MultiMappings = FxHashMap<Box<[u32]>, Box<[u32]>>;
 
-    let json_bytes = serde_json::to_vec(arr.as_slice()).unwrap();
-    std::fs::write(path_out, json_bytes).unwrap();
-}
+/// The context and then the code points of a mapping that only applies after that context, as
+/// from a rule `x|y`
+pub type PrefixKey = (Box<[u32]>, Box<[u32]>);
 
-#[must_use]
-pub fn collect_multis(keys: Tailoring) -> FxHashMap<u64, Box<[u32]>> {
-    collect_entries(keys, |points| points.len() >= 2, pack_code_points, false)
+/// Weights of prefix mappings
+pub type PrefixMappings = FxHashMap<PrefixKey, Box<[u32]>>;
+
+pub fn collect_multis(paths: &Paths, keys: Tailoring) -> Result<MultiMappings> {
+    collect_entries(
+        paths,
+        keys,
+        |points| points.len() >= 2,
+        |points| points.into(),
+        false,
+    )
 }
 
-#[must_use]
-pub fn collect_singles(keys: Tailoring) -> FxHashMap<u32, Box<[u32]>> {
-    collect_entries(keys, |points| points.len() == 1, |points| points[0], true)
+pub fn collect_singles(paths: &Paths, keys: Tailoring) -> Result<FxHashMap<u32, Box<[u32]>>> {
+    collect_entries(
+        paths,
+        keys,
+        |points| points.len() == 1,
+        |points| points[0],
+        true,
+    )
 }
 
 fn collect_entries<K>(
+    paths: &Paths,
     keys: Tailoring,
     include_points: impl Fn(&[u32]) -> bool,
     pack_key: impl Fn(&[u32]) -> K,
     bump: bool,
-) -> FxHashMap<K, Box<[u32]>>
+) -> Result<FxHashMap<K, Box<[u32]>>>
 where
     K: Eq + Hash,
 {
-    let cldr = keys != Tailoring::Ducet;
-    let data = if cldr { &KEYS_CLDR } else { &KEYS_DUCET };
+    let adjust = keys != Tailoring::Ducet && paths.version.adjusts_cldr_primaries();
+    let table = CollationElementTable::read(&paths.allkeys(keys))?;
     let mut map: FxHashMap<K, Box<[u32]>> = FxHashMap::default();
 
-    for line in data.lines() {
-        if line.is_empty() || line.starts_with('@') || line.starts_with('#') {
-            continue;
-        }
-
-        let mut split_at_semicolon = line.split(';');
-        let left_of_semicolon = split_at_semicolon.next().unwrap();
-        let right_of_semicolon = split_at_semicolon.next().unwrap();
-        let left_of_hash = right_of_semicolon.split('#').next().unwrap();
-
-        let mut points = Vec::new();
-        let re_key = regex!(r"[\dA-F]{4,5}");
-        for m in re_key.find_iter(left_of_semicolon) {
-            points.push(u32::from_str_radix(m.as_str(), 16).unwrap());
-        }
-
-        if !include_points(&points) {
+    for entry in &table.entries {
+        if !include_points(&entry.code_points) {
             continue;
         }
 
-        map.insert(pack_key(&points), parse_weights(left_of_hash, cldr, bump));
+        let weights = entry
+            .elements
+            .iter()
+            .map(|&element| pack_element(table.source(entry), element, adjust, bump))
+            .collect::<Result<Box<[u32]>>>()?;
+        map.insert(pack_key(&entry.code_points), weights);
     }
 
-    map
+    Ok(map)
 }
 
-fn parse_weights(left_of_hash: &str, cldr: bool, bump: bool) -> Box<[u32]> {
-    let mut v = Vec::new();
-    let re_weights = regex!(r"[*.\dA-F]{15}");
-    let re_value = regex!(r"[\dA-F]{4}");
-
-    for m in re_weights.find_iter(left_of_hash) {
-        let weights_str = m.as_str();
-        let variable = weights_str.starts_with('*');
-        let mut vals = re_value.find_iter(weights_str);
-
-        let mut primary = u16::from_str_radix(vals.next().unwrap().as_str(), 16).unwrap();
-        if cldr && bump && (BUMP_START..=BUMP_END).contains(&primary) {
-            primary += BUMP;
-        }
-        if cldr && (SHIFT_START..=SHIFT_END).contains(&primary) {
-            primary += SHIFT;
-        }
-
-        let secondary = u16::from_str_radix(vals.next().unwrap().as_str(), 16).unwrap();
-        assert!(secondary <= SEC_MAX);
-
-        let tertiary = u16::from_str_radix(vals.next().unwrap().as_str(), 16).unwrap();
-        assert!(tertiary <= TER_MAX);
-
-        v.push(pack_weights(variable, primary, secondary, tertiary));
+fn pack_element(
+    source: SourceLine,
+    element: CollationElement,
+    adjust: bool,
+    bump: bool,
+) -> Result<u32> {
+    let mut primary = element.primary;
+    if adjust && bump && (BUMP_START..=BUMP_END).contains(&primary) {
+        primary += BUMP;
     }
-
-    v.into_boxed_slice()
-}
-
-#[must_use]
-pub fn pack_code_points(code_points: &[u32]) -> u64 {
-    match code_points.len() {
-        2 => (u64::from(code_points[0]) << 21) | u64::from(code_points[1]),
-        3 => {
-            (u64::from(code_points[0]) << 42)
-                | (u64::from(code_points[1]) << 21)
-                | u64::from(code_points[2])
-        }
-        _ => unreachable!(),
+    if adjust && (SHIFT_START..=SHIFT_END).contains(&primary) {
+        primary += SHIFT;
     }
-}
 
-pub fn unpack_code_points(packed: u64) -> Vec<u32> {
-    if packed >> 42 == 0 {
-        vec![
-            u32::try_from(packed >> 21).unwrap(),
-            u32::try_from(packed & 0x1F_FFFF).unwrap(),
-        ]
-    } else {
-        vec![
-            u32::try_from(packed >> 42).unwrap(),
-            u32::try_from((packed >> 21) & 0x1F_FFFF).unwrap(),
-            u32::try_from(packed & 0x1F_FFFF).unwrap(),
-        ]
-    }
+    let secondary = source.check_weight("secondary", element.secondary, SEC_MAX)?;
+    let tertiary = source.check_weight("tertiary", element.tertiary, TER_MAX)?;
+
+    Ok(pack_weights(element.variable, primary, secondary, tertiary))
 }
 
 #[must_use]
@@ -218,6 +190,22 @@ pub const fn pack_weights(variable: bool, primary: u16, secondary: u16, tertiary
     upper | (lower as u32)
 }
 
+/// Packed weights as collation elements in the style of allkeys.txt, e.g. `[*0209.0020.0002]`
+#[must_use]
+pub fn format_weights(weights: &[u32]) -> String {
+    let mut formatted = String::new();
+    for &packed in weights {
+        let (variable, primary, secondary, tertiary) = unpack_weights(packed);
+        let marker = if variable { '*' } else { '.' };
+        write!(
+            formatted,
+            "[{marker}{primary:04X}.{secondary:04X}.{tertiary:04X}]"
+        )
+        .unwrap();
+    }
+    formatted
+}
+
 #[must_use]
 pub const fn unpack_weights(packed: u32) -> (bool, u16, u16, u16) {
     let primary = (packed >> 16) as u16;
@@ -0,0 +1,214 @@
+#![allow(clippy::missing_errors_doc)]
+
+use crate::{
+    error::{Result, SourceLine},
+    paths::{Paths, read_input},
+    sort_key::{CollationTables, VariableWeighting},
+};
+use feruca::Tailoring;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// The `CollationTest` files that apply to a set of keys, and how each one weights variables. Each
+/// can also be vendored as the `_SHORT` version, which has the same lines without comments.
+const DUCET_TESTS: [(&str, VariableWeighting); 2] = [
+    ("NON_IGNORABLE", VariableWeighting::NonIgnorable),
+    ("SHIFTED", VariableWeighting::Shifted),
+];
+const CLDR_TESTS: [(&str, VariableWeighting); 2] = [
+    ("CLDR_NON_IGNORABLE", VariableWeighting::NonIgnorable),
+    ("CLDR_SHIFTED", VariableWeighting::Shifted),
+];
+
+/// A line whose sort key is lower than that of the line before it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConformanceFailure {
+    /// One-based
+    pub line: usize,
+    pub code_points: Box<[u32]>,
+    pub preceded_by: Box<[u32]>,
+}
+
+impl fmt::Display for ConformanceFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex = |code_points: &[u32]| {
+            let hex: Vec<String> = code_points.iter().map(|cp| format!("{cp:04X}")).collect();
+            hex.join(" ")
+        };
+
+        write!(
+            f,
+            "line {}: {} sorts before {}",
+            self.line,
+            hex(&self.code_points),
+            hex(&self.preceded_by)
+        )
+    }
+}
+
+/// The outcome of running one `CollationTest` file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConformanceReport {
+    pub path: PathBuf,
+    pub checked: usize,
+    /// Lines with surrogate code points, which can't be collated as `char`s
+    pub skipped: usize,
+    pub failures: Vec<ConformanceFailure>,
+}
+
+/// Runs every `CollationTest` file for DUCET or CLDR root that is in the data directory, against
+/// the tables in the output directories. Missing test files are left out of the result.
+pub fn run_conformance(paths: &Paths, keys: Tailoring) -> Result<Vec<ConformanceReport>> {
+    let tests = if keys == Tailoring::Ducet {
+        DUCET_TESTS
+    } else {
+        CLDR_TESTS
+    };
+
+    let tables = CollationTables::read(paths, keys)?;
+    let mut reports = Vec::new();
+
+    for (name, weighting) in tests {
+        let Some(path) = [name.to_string(), format!("{name}_SHORT")]
+            .iter()
+            .map(|name| paths.collation_test(name))
+            .find(|path| path.is_file())
+        else {
+            continue;
+        };
+
+        reports.push(run_collation_test(&tables, &path, weighting)?);
+    }
+
+    Ok(reports)
+}
+
+/// Checks that the lines of a `CollationTest` file have non-decreasing sort keys
+pub fn run_collation_test(
+    tables: &CollationTables,
+    path: &Path,
+    weighting: VariableWeighting,
+) -> Result<ConformanceReport> {
+    check_lines(tables, path, &read_input(path)?, weighting)
+}
+
+fn check_lines(
+    tables: &CollationTables,
+    path: &Path,
+    data: &str,
+    weighting: VariableWeighting,
+) -> Result<ConformanceReport> {
+    let mut report = ConformanceReport {
+        path: path.to_path_buf(),
+        checked: 0,
+        skipped: 0,
+        failures: Vec::new(),
+    };
+    let mut previous: Option<(Box<[u32]>, Vec<u16>)> = None;
+
+    for (i, line) in data.lines().enumerate() {
+        let source = SourceLine::new(path, i);
+        let fields = line.split(['#', ';']).next().unwrap_or_default().trim();
+        if fields.is_empty() || line.starts_with('@') {
+            continue;
+        }
+
+        let code_points = fields
+            .split_whitespace()
+            .map(|hex| source.hex_u32(hex))
+            .collect::<Result<Box<[u32]>>>()?;
+        let Some(chars) = code_points
+            .iter()
+            .map(|&cp| char::from_u32(cp))
+            .collect::<Option<Vec<char>>>()
+        else {
+            report.skipped += 1;
+            continue;
+        };
+
+        let key = tables.sort_key(&chars, weighting);
+        if let Some((preceded_by, previous_key)) = &previous
+            && key < *previous_key
+        {
+            report.failures.push(ConformanceFailure {
+                line: source.line,
+                code_points: code_points.clone(),
+                preceded_by: preceded_by.clone(),
+            });
+        }
+
+        report.checked += 1;
+        previous = Some((code_points, key));
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_lines() {
+        let tables = CollationTables::read(&Paths::default(), Tailoring::default()).unwrap();
+        let check = |data| {
+            check_lines(
+                &tables,
+                Path::new("test.txt"),
+                data,
+                VariableWeighting::Shifted,
+            )
+            .unwrap()
+        };
+
+        let passing = check("@version 16.0.0\n# a A b\n0061; # a\n0041;\n0062;\nD800 0062;\n");
+        assert_eq!((passing.checked, passing.skipped), (3, 1));
+        assert!(passing.failures.is_empty());
+
+        let failing = check("0061;\n0062;\n0041;\n0042;\n");
+        assert_eq!(failing.checked, 4);
+        assert_eq!(
+            failing.failures,
+            [ConformanceFailure {
+                line: 3,
+                code_points: [0x41].into(),
+                preceded_by: [0x62].into(),
+            }]
+        );
+        assert_eq!(
+            failing.failures[0].to_string(),
+            "line 3: 0041 sorts before 0062"
+        );
+    }
+
+    // Run with `cargo test -- --ignored` once the files are in place
+    #[test]
+    #[ignore = "needs CollationTest_NON_IGNORABLE, _SHIFTED, _CLDR_NON_IGNORABLE and \
+                _CLDR_SHIFTED (or their _SHORT versions) vendored in unicode-data/cldr-46_1"]
+    fn conformance() {
+        let paths = Paths::default();
+
+        for keys in [Tailoring::Ducet, Tailoring::default()] {
+            let reports = run_conformance(&paths, keys).unwrap();
+            assert_eq!(
+                reports.len(),
+                2,
+                "missing CollationTest files for {keys:?} in {}",
+                paths.data_dir.display()
+            );
+
+            for report in reports {
+                assert!(report.checked > 0, "{}", report.path.display());
+                assert!(
+                    report.failures.is_empty(),
+                    "{}: {} failures, first {}",
+                    report.path.display(),
+                    report.failures.len(),
+                    report.failures[0]
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,348 @@
+#![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
+
+use crate::{
+    closure::canonical_closure,
+    error::Result,
+    normalization::{build_decomp_table, collect_decomps},
+    paths::{Paths, read_table, write_table},
+    tailor::TailoredMappings,
+    trie::{PrefixMapping, build_prefixed_trie_table},
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, Hash},
+    path::Path,
+};
+
+/// The mappings in which a tailoring differs from the CLDR root, for consulting before the root
+/// trie
+///
+/// Singles are keyed by code point, sorted for binary search. Contractions are keyed by their
+/// code points, stored in a shared pool and sorted in sequence order. The root has no prefix
+/// mappings, so every one of the tailoring's is in the delta, sorted as in the trie.
+#[derive(Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TailoringDelta {
+    pub singles: Box<[DeltaEntry]>,
+    pub multis: Box<[DeltaContraction]>,
+    pub code_points: Box<[u32]>,
+    pub prefixes: Box<[PrefixMapping]>,
+    pub weights: Box<[u32]>,
+}
+
+/// An overridden mapping. A row of no weights marks a root mapping that the tailoring removes.
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DeltaEntry {
+    pub key: u32,
+    pub weight_start: u32,
+    pub weight_len: u16,
+}
+
+/// An overridden contraction, with its code points in the delta's pool
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DeltaContraction {
+    pub code_point_start: u32,
+    pub code_point_len: u16,
+    pub weight_start: u32,
+    pub weight_len: u16,
+}
+
+impl TailoringDelta {
+    #[must_use]
+    pub fn build<S1: BuildHasher, S2: BuildHasher>(
+        root_singles: &HashMap<u32, Box<[u32]>, S1>,
+        root_multis: &HashMap<Box<[u32]>, Box<[u32]>, S2>,
+        mappings: &TailoredMappings,
+    ) -> Self {
+        let mut weights = Vec::new();
+        let mut code_points = Vec::new();
+
+        let singles = changed_rows(root_singles, &mappings.singles)
+            .into_iter()
+            .map(|(&key, row)| {
+                let (weight_start, weight_len) = push_row(&mut weights, row);
+                DeltaEntry {
+                    key,
+                    weight_start,
+                    weight_len,
+                }
+            })
+            .collect();
+        let multis = changed_rows(root_multis, &mappings.multis)
+            .into_iter()
+            .map(|(key, row)| {
+                let (code_point_start, code_point_len) = push_row(&mut code_points, key);
+                let (weight_start, weight_len) = push_row(&mut weights, row);
+                DeltaContraction {
+                    code_point_start,
+                    code_point_len,
+                    weight_start,
+                    weight_len,
+                }
+            })
+            .collect();
+
+        let mut prefixes: Vec<_> = mappings.prefixes.iter().collect();
+        prefixes.sort_unstable_by(|((a_prefix, a), _), ((b_prefix, b), _)| {
+            a.cmp(b).then_with(|| a_prefix.cmp(b_prefix))
+        });
+        let prefixes = prefixes
+            .into_iter()
+            .map(|((prefix, code_points), row)| {
+                let (weight_start, weight_len) = push_row(&mut weights, row);
+                PrefixMapping {
+                    prefix: prefix.clone(),
+                    code_points: code_points.clone(),
+                    weight_start,
+                    weight_len,
+                }
+            })
+            .collect();
+
+        Self {
+            singles,
+            multis,
+            code_points: code_points.into_boxed_slice(),
+            prefixes,
+            weights: weights.into_boxed_slice(),
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        read_table(path)
+    }
+
+    /// The tailored weights of a code point, if the tailoring changes them
+    #[must_use]
+    pub fn single(&self, code_point: u32) -> Option<&[u32]> {
+        let i = self
+            .singles
+            .binary_search_by_key(&code_point, |entry| entry.key)
+            .ok()?;
+        let entry = &self.singles[i];
+        Some(row(&self.weights, entry.weight_start, entry.weight_len))
+    }
+
+    /// The tailored weights of a contraction, if the tailoring changes them
+    #[must_use]
+    pub fn multi(&self, code_points: &[u32]) -> Option<&[u32]> {
+        let i = self
+            .multis
+            .binary_search_by(|entry| self.contraction(entry).cmp(code_points))
+            .ok()?;
+        let entry = &self.multis[i];
+        Some(row(&self.weights, entry.weight_start, entry.weight_len))
+    }
+
+    fn contraction(&self, entry: &DeltaContraction) -> &[u32] {
+        row(
+            &self.code_points,
+            entry.code_point_start,
+            entry.code_point_len,
+        )
+    }
+
+    /// The full tailored mappings, from the root mappings that the delta was built against
+    #[must_use]
+    pub fn apply<S1: BuildHasher, S2: BuildHasher>(
+        &self,
+        root_singles: &HashMap<u32, Box<[u32]>, S1>,
+        root_multis: &HashMap<Box<[u32]>, Box<[u32]>, S2>,
+    ) -> TailoredMappings {
+        let singles = self.singles.iter().map(|entry| {
+            let weights = row(&self.weights, entry.weight_start, entry.weight_len);
+            (entry.key, weights)
+        });
+        let multis = self.multis.iter().map(|entry| {
+            let weights = row(&self.weights, entry.weight_start, entry.weight_len);
+            (Box::from(self.contraction(entry)), weights)
+        });
+
+        let prefixes = self.prefixes.iter().map(|mapping| {
+            let key = (mapping.prefix.clone(), mapping.code_points.clone());
+            let weights = row(&self.weights, mapping.weight_start, mapping.weight_len);
+            (key, weights.into())
+        });
+
+        TailoredMappings {
+            singles: apply_rows(root_singles, singles),
+            multis: apply_rows(root_multis, multis),
+            prefixes: prefixes.collect(),
+        }
+    }
+}
+
+fn row(pool: &[u32], start: u32, len: u16) -> &[u32] {
+    let start = usize::try_from(start).unwrap();
+    &pool[start..start + usize::from(len)]
+}
+
+fn push_row(pool: &mut Vec<u32>, row: &[u32]) -> (u32, u16) {
+    let start = u32::try_from(pool.len()).unwrap();
+    pool.extend_from_slice(row);
+    (start, u16::try_from(row.len()).unwrap())
+}
+
+fn apply_rows<'a, K: Clone + Eq + Hash, S: BuildHasher>(
+    root: &HashMap<K, Box<[u32]>, S>,
+    rows: impl Iterator<Item = (K, &'a [u32])>,
+) -> FxHashMap<K, Box<[u32]>> {
+    let mut mappings: FxHashMap<K, Box<[u32]>> = root
+        .iter()
+        .map(|(k, weights)| (k.clone(), weights.clone()))
+        .collect();
+
+    for (key, row) in rows {
+        match row {
+            [] => mappings.remove(&key),
+            weights => mappings.insert(key, weights.into()),
+        };
+    }
+
+    mappings
+}
+
+// Keys whose rows differ from the root's, sorted, with an empty row for each removed mapping
+fn changed_rows<'a, K: Eq + Hash + Ord, S1: BuildHasher, S2: BuildHasher>(
+    root: &'a HashMap<K, Box<[u32]>, S1>,
+    tailored: &'a HashMap<K, Box<[u32]>, S2>,
+) -> Vec<(&'a K, &'a [u32])> {
+    let changed = tailored
+        .iter()
+        .filter(|&(k, w)| root.get(k) != Some(w))
+        .map(|(k, w)| (k, w.as_ref()));
+    let removed = root
+        .keys()
+        .filter(|&k| !tailored.contains_key(k))
+        .map(|k| (k, [].as_slice()));
+
+    let mut rows: Vec<(&K, &[u32])> = changed.chain(removed).collect();
+    rows.sort_unstable_by_key(|&(k, _)| k);
+    rows
+}
+
+/// Writes a tailored trie to `tailoring/<name>`, and its delta against the CLDR root mappings to
+/// `delta/<name>`
+///
+/// The mappings are first closed under canonical equivalence, with those that differ from the root
+/// treated as changed, so that precomposed characters follow what the tailoring did to their
+/// decompositions.
+pub fn write_tailoring<S1: BuildHasher, S2: BuildHasher>(
+    paths: &Paths,
+    name: &str,
+    root_singles: &HashMap<u32, Box<[u32]>, S1>,
+    root_multis: &HashMap<Box<[u32]>, Box<[u32]>, S2>,
+    mappings: &mut TailoredMappings,
+) -> Result<()> {
+    close_mappings(paths, root_singles, root_multis, mappings)?;
+
+    let table = build_prefixed_trie_table(&mappings.singles, &mappings.multis, &mappings.prefixes)?;
+    write_table(&paths.out(&format!("tailoring/{name}")), &table)?;
+
+    let delta = TailoringDelta::build(root_singles, root_multis, mappings);
+    write_table(&paths.out(&format!("delta/{name}")), &delta)
+}
+
+fn close_mappings<S1: BuildHasher, S2: BuildHasher>(
+    paths: &Paths,
+    root_singles: &HashMap<u32, Box<[u32]>, S1>,
+    root_multis: &HashMap<Box<[u32]>, Box<[u32]>, S2>,
+    mappings: &mut TailoredMappings,
+) -> Result<()> {
+    let changed_singles = (mappings.singles.iter())
+        .filter(|&(k, w)| root_singles.get(k) != Some(w))
+        .map(|(&k, _)| Box::from([k]));
+    let changed_multis = (mappings.multis.iter())
+        .filter(|&(k, w)| root_multis.get(k) != Some(w))
+        .map(|(k, _)| k.clone());
+    let changed: FxHashSet<Box<[u32]>> = changed_singles.chain(changed_multis).collect();
+
+    let decomp = build_decomp_table(&collect_decomps(paths)?);
+    canonical_closure(
+        &decomp,
+        &mut mappings.singles,
+        &mut mappings.multis,
+        &changed,
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collation::{PrefixMappings, collect_multis, collect_singles},
+        rules::CollationRules,
+        tailor::apply_rules,
+    };
+    use feruca::Tailoring;
+
+    #[test]
+    fn delta_round_trip() {
+        let root_singles: FxHashMap<u32, Box<[u32]>> =
+            [(0x41, [1].into()), (0x43, [3].into()), (0x48, [8].into())]
+                .into_iter()
+                .collect();
+        let root_multis: FxHashMap<Box<[u32]>, Box<[u32]>> = [
+            ([0x41, 0x300].into(), [10].into()),
+            ([0x41, 0x301].into(), [11].into()),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut singles = root_singles.clone();
+        singles.insert(0x43, [4].into());
+        singles.insert(0x44, [5, 6].into());
+        let mut multis = root_multis.clone();
+        multis.remove([0x41, 0x301].as_slice());
+        multis.insert([0x43, 0x48].into(), [9].into());
+        multis.insert([0x43, 0x48, 0x41, 0x300].into(), [7, 8].into());
+        let mut prefixes = PrefixMappings::default();
+        prefixes.insert(([0x41].into(), [0x48].into()), [12].into());
+        let mappings = TailoredMappings {
+            singles,
+            multis,
+            prefixes,
+        };
+
+        let delta = TailoringDelta::build(&root_singles, &root_multis, &mappings);
+        let bytes = postcard::to_allocvec(&delta).unwrap();
+        let delta: TailoringDelta = postcard::from_bytes(&bytes).unwrap();
+
+        assert_eq!(delta.singles.len(), 2);
+        assert_eq!(delta.multis.len(), 3);
+        assert_eq!(delta.single(0x43), Some([4].as_slice()));
+        assert_eq!(delta.single(0x41), None);
+        assert_eq!(delta.multi(&[0x41, 0x301]), Some([].as_slice()));
+        assert_eq!(
+            delta.multi(&[0x43, 0x48, 0x41, 0x300]),
+            Some([7, 8].as_slice())
+        );
+        assert_eq!(delta.multi(&[0x43, 0x48, 0x41]), None);
+        assert_eq!(delta.prefixes.len(), 1);
+        let applied = delta.apply(&root_singles, &root_multis);
+        assert_eq!(applied.singles, mappings.singles);
+        assert_eq!(applied.multis, mappings.multis);
+        assert_eq!(applied.prefixes, mappings.prefixes);
+    }
+
+    // A tailoring that moves one letter carries that letter and its equivalents, not the weights
+    // that the allocation had to shift around it
+    #[test]
+    fn small_tailoring_small_delta() {
+        let paths = Paths::default();
+        let root_singles = collect_singles(&paths, Tailoring::default()).unwrap();
+        let root_multis = collect_multis(&paths, Tailoring::default()).unwrap();
+
+        let rules = CollationRules::read(&paths, "es").unwrap();
+        let mut mappings = apply_rules(&paths, &rules, &root_singles, &root_multis).unwrap();
+        close_mappings(&paths, &root_singles, &root_multis, &mut mappings).unwrap();
+        let delta = TailoringDelta::build(&root_singles, &root_multis, &mappings);
+
+        assert_eq!(delta.singles.len(), 2);
+        assert_eq!(delta.multis.len(), 2);
+        assert!(delta.multi(&[0x6E, 0x303]).is_some());
+        assert!(postcard::to_allocvec(&delta).unwrap().len() < 128);
+    }
+}
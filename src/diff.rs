@@ -0,0 +1,304 @@
+#![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
+
+use crate::{
+    collation::format_weights,
+    common::CODE_POINT_COUNT,
+    error::{Error, Result},
+    names::CharacterNames,
+    normalization::{DecompTable, FcdTable},
+    paths::read_table,
+    trie::CollationTrieTable,
+    variable::VariableTable,
+};
+use rustc_hash::FxHashMap;
+use serde::Serialize;
+use std::{
+    collections::BTreeSet,
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+// Outputs that aren't keyed by code point, or that only repackage the others
+const SKIPPED_OUTPUTS: [&str; 2] = ["implicit", "bundle"];
+
+// Describes the other outputs rather than being one
+const MANIFEST: &str = "tailoring/manifest.json";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TableKind {
+    Trie,
+    Decomp,
+    Fcd,
+    Variable,
+}
+
+impl TableKind {
+    /// The kind of a table from its name in the output directory
+    #[must_use]
+    pub fn of(name: &str) -> Self {
+        match name {
+            "decomp" => Self::Decomp,
+            "fcd" => Self::Fcd,
+            "variable" => Self::Variable,
+            _ => Self::Trie,
+        }
+    }
+}
+
+/// What one code point or contraction maps to in each of two tables
+///
+/// The values are packed weights for tries, code points for decompositions, and the first and last
+/// combining classes for FCD; a variable code point has an empty value.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Change {
+    pub code_points: Box<[u32]>,
+    pub old: Option<Box<[u32]>>,
+    pub new: Option<Box<[u32]>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TableDiff {
+    /// Path relative to the output directory, e.g. `cldr_root` or `tailoring/sv`
+    pub table: String,
+    pub kind: TableKind,
+    pub changes: Vec<Change>,
+}
+
+/// The differences between two output directories
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct OutputDiff {
+    pub old_dir: PathBuf,
+    pub new_dir: PathBuf,
+    /// Tables in only one of the directories
+    pub only_old: Vec<String>,
+    pub only_new: Vec<String>,
+    pub tables: Vec<TableDiff>,
+}
+
+impl OutputDiff {
+    /// A summary line for each table, then a line for each change
+    #[must_use]
+    pub fn report(&self, names: &CharacterNames) -> String {
+        let mut report = String::new();
+
+        for table in &self.only_old {
+            writeln!(report, "{table}: only in {}", self.old_dir.display()).unwrap();
+        }
+        for table in &self.only_new {
+            writeln!(report, "{table}: only in {}", self.new_dir.display()).unwrap();
+        }
+
+        for diff in &self.tables {
+            let count = |f: fn(&Change) -> bool| diff.changes.iter().filter(|c| f(c)).count();
+            writeln!(
+                report,
+                "{}: {} changed, {} added, {} removed",
+                diff.table,
+                count(|c| c.old.is_some() && c.new.is_some()),
+                count(|c| c.old.is_none()),
+                count(|c| c.new.is_none()),
+            )
+            .unwrap();
+
+            for change in &diff.changes {
+                let format = |value: &[u32]| format_value(diff.kind, value);
+                let described = names.describe(&change.code_points);
+                let line = match (&change.old, &change.new) {
+                    (Some(old), Some(new)) => {
+                        format!("~ {described}: {} -> {}", format(old), format(new))
+                    }
+                    (None, Some(new)) => format!("+ {described}: {}", format(new)),
+                    (Some(old), None) => format!("- {described}: {}", format(old)),
+                    (None, None) => continue,
+                };
+                writeln!(report, "  {line}").unwrap();
+            }
+        }
+
+        report
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| Error::serialization(&self.new_dir, e))
+    }
+}
+
+fn format_value(kind: TableKind, value: &[u32]) -> String {
+    match kind {
+        TableKind::Trie => format_weights(value),
+        TableKind::Decomp => {
+            let hex: Vec<String> = value.iter().map(|cp| format!("{cp:04X}")).collect();
+            hex.join(" ")
+        }
+        TableKind::Fcd => format!("ccc {} .. {}", value[0], value[1]),
+        TableKind::Variable => "variable".to_string(),
+    }
+}
+
+/// Compares every table that two output directories have in common, code point by code point
+pub fn diff_outputs(old_dir: &Path, new_dir: &Path) -> Result<OutputDiff> {
+    let mut old_tables = list_outputs(old_dir)?;
+    let mut new_tables = list_outputs(new_dir)?;
+    for skipped in SKIPPED_OUTPUTS {
+        old_tables.remove(skipped);
+        new_tables.remove(skipped);
+    }
+
+    let mut diff = OutputDiff {
+        old_dir: old_dir.to_path_buf(),
+        new_dir: new_dir.to_path_buf(),
+        only_old: old_tables.difference(&new_tables).cloned().collect(),
+        only_new: new_tables.difference(&old_tables).cloned().collect(),
+        ..OutputDiff::default()
+    };
+
+    for table in old_tables.intersection(&new_tables) {
+        let kind = TableKind::of(table);
+        let old = read_values(&old_dir.join(table), kind)?;
+        let new = read_values(&new_dir.join(table), kind)?;
+        diff.tables.push(TableDiff {
+            table: table.clone(),
+            kind,
+            changes: diff_values(old, new),
+        });
+    }
+
+    Ok(diff)
+}
+
+/// Top-level tables and tailorings, by path relative to the output directory
+pub fn list_outputs(dir: &Path) -> Result<BTreeSet<String>> {
+    let mut tables = BTreeSet::new();
+
+    for subdir in ["", "tailoring"] {
+        let path = dir.join(subdir);
+        if subdir == "tailoring" && !path.is_dir() {
+            continue;
+        }
+
+        for entry in std::fs::read_dir(&path).map_err(|e| Error::io(&path, e))? {
+            let entry = entry.map_err(|e| Error::io(&path, e))?;
+            if !entry.path().is_file() {
+                continue;
+            }
+
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let name = if subdir.is_empty() {
+                name
+            } else {
+                format!("{subdir}/{name}")
+            };
+            if name != MANIFEST {
+                tables.insert(name);
+            }
+        }
+    }
+
+    Ok(tables)
+}
+
+type Values = FxHashMap<Box<[u32]>, Box<[u32]>>;
+
+fn read_values(path: &Path, kind: TableKind) -> Result<Values> {
+    let code_points = 0..u32::try_from(CODE_POINT_COUNT).unwrap();
+    let single = |code_point: u32| -> Box<[u32]> { vec![code_point].into() };
+
+    let values = match kind {
+        TableKind::Trie => {
+            let table: CollationTrieTable = read_table(path)?;
+            table
+                .mappings()
+                .into_iter()
+                .map(|(code_points, weights)| (code_points, weights.into()))
+                .collect()
+        }
+        TableKind::Decomp => {
+            let table: DecompTable = read_table(path)?;
+            code_points
+                .filter_map(|cp| Some((single(cp), table.get(cp)?.into())))
+                .collect()
+        }
+        TableKind::Fcd => {
+            let table: FcdTable = read_table(path)?;
+            code_points
+                .filter_map(|cp| {
+                    let [first, last] = table.get(cp).to_be_bytes();
+                    let value = [u32::from(first), u32::from(last)];
+                    (value != [0, 0]).then(|| (single(cp), value.into()))
+                })
+                .collect()
+        }
+        TableKind::Variable => {
+            let table: VariableTable = read_table(path)?;
+            code_points
+                .filter(|&cp| table.contains(cp))
+                .map(|cp| (single(cp), Box::default()))
+                .collect()
+        }
+    };
+
+    Ok(values)
+}
+
+fn diff_values(mut old: Values, new: Values) -> Vec<Change> {
+    let mut changes: Vec<Change> = Vec::new();
+
+    for (code_points, new_value) in new {
+        match old.remove(&code_points) {
+            Some(old_value) if old_value == new_value => {}
+            old_value => changes.push(Change {
+                code_points,
+                old: old_value,
+                new: Some(new_value),
+            }),
+        }
+    }
+    changes.extend(old.into_iter().map(|(code_points, old_value)| Change {
+        code_points,
+        old: Some(old_value),
+        new: None,
+    }));
+
+    changes.sort_unstable_by(|a, b| a.code_points.cmp(&b.code_points));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes() {
+        let values = |entries: &[(&[u32], &[u32])]| -> Values {
+            entries
+                .iter()
+                .map(|&(code_points, value)| (code_points.into(), value.into()))
+                .collect()
+        };
+
+        let old = values(&[(&[0x41], &[1]), (&[0x42], &[2]), (&[0x4C, 0xB7], &[3])]);
+        let new = values(&[(&[0x41], &[1]), (&[0x42], &[4]), (&[0x43], &[5])]);
+
+        assert_eq!(
+            diff_values(old, new),
+            [
+                Change {
+                    code_points: [0x42].into(),
+                    old: Some([2].into()),
+                    new: Some([4].into()),
+                },
+                Change {
+                    code_points: [0x43].into(),
+                    old: None,
+                    new: Some([5].into()),
+                },
+                Change {
+                    code_points: [0x4C, 0xB7].into(),
+                    old: Some([3].into()),
+                    new: None,
+                },
+            ]
+        );
+    }
+}
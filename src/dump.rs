@@ -0,0 +1,99 @@
+use crate::{
+    collation::format_weights,
+    names::CharacterNames,
+    trie::{CollationTrieTable, ENTRY_CONTRACTION, entry_tag},
+};
+use std::fmt::Write;
+
+/// Renders a trie in the style of `allkeys.txt`, one mapping per line, e.g.
+///
+/// `004C 00B7 ; [.21E5.0020.0008][.0000.011C.0002] ; contraction # LATIN CAPITAL LETTER L, ...`
+///
+/// The tag is that of the first code point's entry, so a contraction root shows as a contraction
+/// on its own line too. Code points on the low fast path only appear where they start a
+/// contraction or prefix mapping, and implicit weights don't appear at all. Prefix mappings come
+/// last, with the prefix before a `|`, as in `30A1 | 30FC ; [...] ; prefix # ...`.
+#[must_use]
+pub fn dump_trie(table: &CollationTrieTable, names: &CharacterNames) -> String {
+    let mut dump = String::new();
+
+    for (code_points, weights) in table.mappings() {
+        let tag = if entry_tag(table.entry(code_points[0])) == ENTRY_CONTRACTION {
+            "contraction"
+        } else {
+            "simple"
+        };
+        writeln!(
+            dump,
+            "{} ; {} ; {tag} # {}",
+            hex(&code_points),
+            format_weights(weights),
+            describe(names, &code_points)
+        )
+        .unwrap();
+    }
+
+    for (prefix, code_points, weights) in table.prefix_mappings() {
+        writeln!(
+            dump,
+            "{} | {} ; {} ; prefix # {} | {}",
+            hex(prefix),
+            hex(code_points),
+            format_weights(weights),
+            describe(names, prefix),
+            describe(names, code_points)
+        )
+        .unwrap();
+    }
+
+    dump
+}
+
+fn hex(code_points: &[u32]) -> String {
+    let hex: Vec<String> = code_points.iter().map(|cp| format!("{cp:04X}")).collect();
+    hex.join(" ")
+}
+
+fn describe(names: &CharacterNames, code_points: &[u32]) -> String {
+    let described: Vec<&str> = code_points
+        .iter()
+        .map(|&cp| names.get(cp).unwrap_or("<unassigned>"))
+        .collect();
+    described.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collation::{PrefixMappings, pack_weights},
+        trie::build_prefixed_trie_table,
+    };
+    use rustc_hash::FxHashMap;
+
+    #[test]
+    fn dumps_mappings() {
+        let l = pack_weights(false, 0x21E5, 0x20, 0x08);
+        let dot = pack_weights(true, 0x0222, 0x20, 0x02);
+        let mark = pack_weights(false, 0, 0x011C, 0x02);
+
+        let singles: FxHashMap<u32, Box<[u32]>> = [(0x4C, [l].into()), (0xB7, [dot].into())]
+            .into_iter()
+            .collect();
+        let mut multis: FxHashMap<Box<[u32]>, Box<[u32]>> = FxHashMap::default();
+        multis.insert([0x4C, 0xB7].into(), [l, mark].into());
+        let mut prefixes = PrefixMappings::default();
+        prefixes.insert(([0x4C].into(), [0x4C].into()), [mark].into());
+        let table = build_prefixed_trie_table(&singles, &multis, &prefixes).unwrap();
+
+        assert_eq!(
+            dump_trie(&table, &CharacterNames::default()),
+            "\
+004C ; [.21E5.0020.0008] ; contraction # <unassigned>
+004C 00B7 ; [.21E5.0020.0008][.0000.011C.0002] ; contraction # <unassigned>, <unassigned>
+00B7 ; [*0222.0020.0002] ; simple # <unassigned>
+004C | 004C ; [.0000.011C.0002] ; prefix # <unassigned> | <unassigned>
+"
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// A file could not be read or written
+    Io { path: PathBuf, source: io::Error },
+    /// A line of an input file did not have the expected form
+    Parse {
+        file: PathBuf,
+        line: usize,
+        message: String,
+    },
+    /// A secondary or tertiary weight exceeds what the packed format can hold
+    WeightOutOfRange {
+        file: PathBuf,
+        line: usize,
+        level: &'static str,
+        value: u16,
+        max: u16,
+    },
+    /// A contraction or prefix mapping starts with a code point that has no mapping of its own
+    MissingContractionPrefix { code_point: u32 },
+    /// A contraction has more code points than the trie can record as its length
+    ContractionTooLong { code_point: u32, len: usize },
+    /// An input file parsed, but its contents can't be used as they are
+    Invalid { file: PathBuf, message: String },
+    /// A table could not be encoded, or a generated table could not be decoded
+    Serialization { path: PathBuf, message: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            Self::Parse {
+                file,
+                line,
+                message,
+            } => write!(f, "{}:{line}: {message}", file.display()),
+            Self::WeightOutOfRange {
+                file,
+                line,
+                level,
+                value,
+                max,
+            } => write!(
+                f,
+                "{}:{line}: {level} weight {value:04X} exceeds maximum {max:04X}",
+                file.display()
+            ),
+            Self::MissingContractionPrefix { code_point } => write!(
+                f,
+                "missing single mapping for U+{code_point:04X}, which starts other mappings"
+            ),
+            Self::ContractionTooLong { code_point, len } => write!(
+                f,
+                "contraction of {len} code points starting with U+{code_point:04X} exceeds the \
+                 maximum of {}",
+                u8::MAX
+            ),
+            Self::Invalid { file, message } => write!(f, "{}: {message}", file.display()),
+            Self::Serialization { path, message } => write!(f, "{}: {message}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl Error {
+    pub(crate) fn io(path: &Path, source: io::Error) -> Self {
+        Self::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    pub(crate) fn invalid(file: &Path, message: impl Into<String>) -> Self {
+        Self::Invalid {
+            file: file.to_path_buf(),
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn serialization(path: &Path, message: impl fmt::Display) -> Self {
+        Self::Serialization {
+            path: path.to_path_buf(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// A position in an input file, for attaching to parse errors
+#[derive(Clone, Copy)]
+pub struct SourceLine<'a> {
+    pub file: &'a Path,
+    pub line: usize,
+}
+
+impl<'a> SourceLine<'a> {
+    pub const fn new(file: &'a Path, index: usize) -> Self {
+        Self {
+            file,
+            line: index + 1,
+        }
+    }
+
+    pub fn error(self, message: impl Into<String>) -> Error {
+        Error::Parse {
+            file: self.file.to_path_buf(),
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    pub fn hex_u32(self, s: &str) -> Result<u32> {
+        u32::from_str_radix(s, 16).map_err(|_| self.error(format!("invalid code point: {s}")))
+    }
+
+    pub fn hex_u16(self, s: &str) -> Result<u16> {
+        u16::from_str_radix(s, 16).map_err(|_| self.error(format!("invalid weight: {s}")))
+    }
+
+    pub fn check_weight(self, level: &'static str, value: u16, max: u16) -> Result<u16> {
+        if value > max {
+            return Err(Error::WeightOutOfRange {
+                file: self.file.to_path_buf(),
+                line: self.line,
+                level,
+                value,
+                max,
+            });
+        }
+
+        Ok(value)
+    }
+}
@@ -0,0 +1,738 @@
+#![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
+
+use crate::{
+    collation::{
+        PrefixMappings, collect_multis, collect_singles, low_from_mappings, pack_weights,
+        unpack_weights,
+    },
+    error::{Error, Result, SourceLine},
+    implicit::{HAN_CORE_BASE, HAN_OTHER_BASE, implicit_primaries},
+    paths::{Paths, read_input, write_json, write_table},
+    trie::build_prefixed_trie_table,
+};
+use feruca::Tailoring;
+use rustc_hash::FxHashMap;
+use std::{
+    collections::BTreeSet,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+// Fractional weights are byte strings, while feruca packs each collation element into a u32. Since
+// the root collation has only about 36,000 distinct primaries, 268 secondaries and 51 tertiaries,
+// each level can be renumbered in order. The common secondary and tertiary become 0020 and 0002,
+// as in allkeys, so that computed implicit weights still fit in.
+pub const SEC_COMMON: u16 = 0x20;
+pub const TER_COMMON: u16 = 0x02;
+pub const SEC_LIMIT: u16 = 1 << 9;
+pub const TER_LIMIT: u16 = 1 << 6;
+
+// Primaries with a lead byte below the implicit range have to stay below the implicit bases (FB00
+// and up), and trailing primaries go at the very top, as U+FFFD and U+FFFF do in allkeys.
+const FIRST_TRAILING_PRIMARY: u16 = 0xFFFD;
+pub const IMPLICIT_FLOOR: u16 = 0xFB00;
+
+// Han in these blocks gets the core implicit base, and all other Han the other one
+const CORE_HAN_BLOCKS: [RangeInclusive<u32>; 2] = [0x4E00..=0x9FFF, 0xF900..=0xFAFF];
+
+// Contractions starting with these noncharacters mark script and group boundaries for ICU
+const BOUNDARY_MARKERS: RangeInclusive<u32> = 0xFDD0..=0xFDD1;
+
+/// The contents of a `FractionalUCA.txt` or `FractionalUCA_SHORT.txt` file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FractionalTable {
+    pub path: PathBuf,
+    /// From `[UCA version = ...]`
+    pub uca_version: Option<String>,
+    pub unified_ideographs: Vec<RangeInclusive<u32>>,
+    /// The reordering groups and scripts that share each lead byte
+    pub top_bytes: Vec<TopByte>,
+    pub reordering_tokens: Vec<ReorderingToken>,
+    /// Lines such as `[first regular [0C 04, 05, 05]]`
+    pub boundaries: Vec<Boundary>,
+    pub variable_top: Box<[u8]>,
+    /// Lines such as `[fixed first implicit byte E0]`
+    pub fixed_bytes: Vec<(String, u8)>,
+    /// Mappings in file order
+    pub entries: Vec<FractionalEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TopByte {
+    pub lead: u8,
+    pub tokens: Vec<String>,
+}
+
+/// A script or special group, with the number of primaries it has under each lead byte
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReorderingToken {
+    pub token: String,
+    pub leads: Vec<(u8, u32)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Boundary {
+    /// E.g. "last variable"
+    pub name: String,
+    /// Weights given as `X` are left empty
+    pub weights: FractionalWeights,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FractionalEntry {
+    /// Context that must precede the code points, as in `004C | 00B7`; usually empty
+    pub prefix: Box<[u32]>,
+    pub code_points: Box<[u32]>,
+    pub elements: Box<[FractionalElement]>,
+    /// One-based line number in the source file
+    pub line: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FractionalWeights {
+    pub primary: Box<[u8]>,
+    pub secondary: Box<[u8]>,
+    pub tertiary: Box<[u8]>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FractionalElement {
+    Explicit(FractionalWeights),
+    /// `[U+4E00]`, `[U+4E00, 10]` or `[U+4E36, 70, 05]`: the implicit weights of a Han character,
+    /// with the given secondary and tertiary on the first element. `None` is the common weight.
+    Implicit {
+        code_point: u32,
+        secondary: Option<Box<[u8]>>,
+        tertiary: Option<Box<[u8]>>,
+    },
+}
+
+impl FractionalTable {
+    pub fn read(path: &Path) -> Result<Self> {
+        let data = read_input(path)?;
+        Self::parse(path, &data)
+    }
+
+    /// Parses the text of a `FractionalUCA` file; `path` is used only for error messages
+    pub fn parse(path: &Path, data: &str) -> Result<Self> {
+        let mut table = Self {
+            path: path.to_path_buf(),
+            uca_version: None,
+            unified_ideographs: Vec::new(),
+            top_bytes: Vec::new(),
+            reordering_tokens: Vec::new(),
+            boundaries: Vec::new(),
+            variable_top: Box::default(),
+            fixed_bytes: Vec::new(),
+            entries: Vec::new(),
+        };
+
+        for (i, line) in data.lines().enumerate() {
+            let source = SourceLine::new(path, i);
+            let content = line.split('#').next().unwrap_or_default().trim();
+            if content.is_empty() {
+                continue;
+            }
+
+            if let Some(directive) = content.strip_prefix('[') {
+                let directive = directive
+                    .strip_suffix(']')
+                    .ok_or_else(|| source.error("unterminated directive"))?;
+                table.parse_directive(source, directive.trim())?;
+                continue;
+            }
+
+            let (left_of_semicolon, right_of_semicolon) = content
+                .split_once(';')
+                .ok_or_else(|| source.error("missing semicolon"))?;
+            let (prefix, code_points) = left_of_semicolon
+                .split_once('|')
+                .unwrap_or(("", left_of_semicolon));
+
+            let code_points = parse_code_points(source, code_points)?;
+            if code_points.is_empty() {
+                return Err(source.error("no code points"));
+            }
+
+            table.entries.push(FractionalEntry {
+                prefix: parse_code_points(source, prefix)?,
+                code_points,
+                elements: parse_elements(source, right_of_semicolon.trim())?,
+                line: source.line,
+            });
+        }
+
+        Ok(table)
+    }
+
+    fn parse_directive(&mut self, source: SourceLine, directive: &str) -> Result<()> {
+        let (keyword, rest) = directive
+            .split_once(char::is_whitespace)
+            .unwrap_or((directive, ""));
+        let rest = rest.trim();
+
+        match keyword {
+            "UCA" => {
+                let version = rest
+                    .strip_prefix("version =")
+                    .ok_or_else(|| source.error(format!("malformed directive: {directive}")))?;
+                self.uca_version = Some(version.trim().to_string());
+            }
+            "Unified_Ideograph" => {
+                for range in rest.split_whitespace() {
+                    let (first, last) = range.split_once("..").unwrap_or((range, range));
+                    self.unified_ideographs
+                        .push(source.hex_u32(first)?..=source.hex_u32(last)?);
+                }
+            }
+            "top_byte" => {
+                let mut fields = rest.split_whitespace();
+                let lead = fields
+                    .next()
+                    .ok_or_else(|| source.error("missing lead byte"))?;
+                self.top_bytes.push(TopByte {
+                    lead: hex_u8(source, lead)?,
+                    tokens: fields.map(ToString::to_string).collect(),
+                });
+            }
+            "reorderingTokens" => {
+                let mut fields = rest.split_whitespace();
+                let token = fields.next().ok_or_else(|| source.error("missing token"))?;
+                let leads = fields
+                    .map(|field| {
+                        let (lead, count) = field
+                            .split_once('=')
+                            .ok_or_else(|| source.error(format!("malformed lead byte: {field}")))?;
+                        let count = count
+                            .parse()
+                            .map_err(|_| source.error(format!("malformed count: {field}")))?;
+                        Ok((hex_u8(source, lead)?, count))
+                    })
+                    .collect::<Result<_>>()?;
+                self.reordering_tokens.push(ReorderingToken {
+                    token: token.to_string(),
+                    leads,
+                });
+            }
+            "variable" => {
+                let top = rest
+                    .strip_prefix("top =")
+                    .ok_or_else(|| source.error(format!("malformed directive: {directive}")))?;
+                self.variable_top = parse_bytes(source, top)?;
+            }
+            "first" | "last" => {
+                let (name, weights) = directive
+                    .split_once('[')
+                    .ok_or_else(|| source.error(format!("malformed boundary: {directive}")))?;
+                let weights = weights
+                    .strip_suffix(']')
+                    .ok_or_else(|| source.error(format!("malformed boundary: {directive}")))?;
+                self.boundaries.push(Boundary {
+                    name: name.trim().to_string(),
+                    weights: parse_weights(source, weights)?,
+                });
+            }
+            "fixed" => {
+                let (name, byte) = rest
+                    .rsplit_once(' ')
+                    .ok_or_else(|| source.error(format!("malformed directive: {directive}")))?;
+                self.fixed_bytes
+                    .push((name.to_string(), hex_u8(source, byte)?));
+            }
+            // Radical-stroke order for Han, which only the unihan tailorings use, and counts of
+            // general categories per lead byte
+            "radical" | "categories" => {}
+            _ => return Err(source.error(format!("unknown directive: [{keyword} ...]"))),
+        }
+
+        Ok(())
+    }
+
+    pub fn boundary(&self, name: &str) -> Result<&FractionalWeights> {
+        self.boundaries
+            .iter()
+            .find(|boundary| boundary.name == name)
+            .map(|boundary| &boundary.weights)
+            .ok_or_else(|| Error::invalid(&self.path, format!("missing [{name}] boundary")))
+    }
+
+    /// The implicit primaries of a Han character, using the file's `[Unified_Ideograph]` list
+    #[must_use]
+    pub fn han_primaries(&self, code_point: u32) -> Option<(u16, u16)> {
+        if !self
+            .unified_ideographs
+            .iter()
+            .any(|r| r.contains(&code_point))
+        {
+            return None;
+        }
+
+        let base = if CORE_HAN_BLOCKS.iter().any(|r| r.contains(&code_point)) {
+            HAN_CORE_BASE
+        } else {
+            HAN_OTHER_BASE
+        };
+        Some(implicit_primaries(base, code_point))
+    }
+
+    /// E.g. `fixed_byte("first implicit byte")`
+    pub fn fixed_byte(&self, name: &str) -> Result<u8> {
+        self.fixed_bytes
+            .iter()
+            .find(|(fixed, _)| fixed == name)
+            .map(|&(_, byte)| byte)
+            .ok_or_else(|| Error::invalid(&self.path, format!("missing [fixed {name}]")))
+    }
+}
+
+fn parse_code_points(source: SourceLine, s: &str) -> Result<Box<[u32]>> {
+    s.split_whitespace().map(|cp| source.hex_u32(cp)).collect()
+}
+
+fn hex_u8(source: SourceLine, s: &str) -> Result<u8> {
+    u8::from_str_radix(s, 16).map_err(|_| source.error(format!("invalid byte: {s}")))
+}
+
+// E.g. "0B 8C 64"; `X` stands for any weight in the boundary lines
+fn parse_bytes(source: SourceLine, s: &str) -> Result<Box<[u8]>> {
+    s.split_whitespace()
+        .filter(|&byte| byte != "X")
+        .map(|byte| hex_u8(source, byte))
+        .collect()
+}
+
+// E.g. "0B 8C 64, 05, 05"
+fn parse_weights(source: SourceLine, s: &str) -> Result<FractionalWeights> {
+    let mut levels = s.split(',');
+    let mut next_level = || {
+        levels
+            .next()
+            .ok_or_else(|| source.error(format!("malformed collation element: [{s}]")))
+            .and_then(|level| parse_bytes(source, level))
+    };
+
+    Ok(FractionalWeights {
+        primary: next_level()?,
+        secondary: next_level()?,
+        tertiary: next_level()?,
+    })
+}
+
+// E.g. "[09 7A, 05, 17][U+4E00, 10][09 7C, 05, 17]"
+fn parse_elements(source: SourceLine, s: &str) -> Result<Box<[FractionalElement]>> {
+    let mut elements = Vec::new();
+    let mut rest = s;
+
+    while !rest.is_empty() {
+        let (element, after) = rest
+            .strip_prefix('[')
+            .and_then(|r| r.split_once(']'))
+            .ok_or_else(|| source.error(format!("malformed collation elements: {s}")))?;
+
+        let element = if let Some(reference) = element.strip_prefix("U+") {
+            let mut fields = reference.split(',').map(str::trim);
+            let code_point = source.hex_u32(fields.next().unwrap_or_default())?;
+            let (secondary, tertiary) = match (fields.next(), fields.next()) {
+                (None, _) => (None, None),
+                (Some(t), None) => (None, Some(parse_bytes(source, t)?)),
+                (Some(s), Some(t)) => {
+                    (Some(parse_bytes(source, s)?), Some(parse_bytes(source, t)?))
+                }
+            };
+            FractionalElement::Implicit {
+                code_point,
+                secondary,
+                tertiary,
+            }
+        } else {
+            FractionalElement::Explicit(parse_weights(source, element)?)
+        };
+
+        elements.push(element);
+        rest = after.trim_start();
+    }
+
+    if elements.is_empty() {
+        return Err(source.error("no collation elements"));
+    }
+
+    Ok(elements.into_boxed_slice())
+}
+
+/// Renumbers fractional weights into the levels of feruca's packed collation elements
+pub struct FractionalWeightMap {
+    primaries: FxHashMap<Box<[u8]>, u16>,
+    secondaries: FxHashMap<Box<[u8]>, u16>,
+    tertiaries: FxHashMap<Box<[u8]>, u16>,
+    last_variable: Box<[u8]>,
+}
+
+impl FractionalWeightMap {
+    pub fn new(table: &FractionalTable, entries: &[&FractionalEntry]) -> Result<Self> {
+        let secondary_common: Box<[u8]> = Box::from([table.fixed_byte("secondary common byte")?]);
+        let tertiary_common: Box<[u8]> = Box::from([table.fixed_byte("tertiary common byte")?]);
+
+        let mut primaries = BTreeSet::new();
+        let mut secondaries = BTreeSet::from([secondary_common.clone()]);
+        let mut tertiaries = BTreeSet::from([tertiary_common.clone()]);
+
+        for entry in entries {
+            for element in &entry.elements {
+                match element {
+                    FractionalElement::Explicit(weights) => {
+                        primaries.insert(weights.primary.clone());
+                        secondaries.insert(weights.secondary.clone());
+                        tertiaries.insert(weights.tertiary.clone());
+                    }
+                    FractionalElement::Implicit {
+                        secondary,
+                        tertiary,
+                        ..
+                    } => {
+                        secondaries.extend(secondary.clone());
+                        tertiaries.extend(tertiary.clone());
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            primaries: number_primaries(table, primaries)?,
+            secondaries: number_level(
+                table,
+                "secondary",
+                secondaries,
+                &secondary_common,
+                SEC_COMMON,
+                SEC_LIMIT,
+            )?,
+            tertiaries: number_level(
+                table,
+                "tertiary",
+                tertiaries,
+                &tertiary_common,
+                TER_COMMON,
+                TER_LIMIT,
+            )?,
+            last_variable: table.boundary("last variable")?.primary.clone(),
+        })
+    }
+
+    /// The packed collation elements for one mapping
+    pub fn pack(&self, table: &FractionalTable, entry: &FractionalEntry) -> Result<Box<[u32]>> {
+        let elements = &entry.elements;
+        let mut packed = Vec::with_capacity(elements.len());
+
+        for element in elements {
+            match element {
+                FractionalElement::Explicit(weights) => {
+                    let variable =
+                        !weights.primary.is_empty() && weights.primary <= self.last_variable;
+                    packed.push(pack_weights(
+                        variable,
+                        self.primaries[&weights.primary],
+                        self.secondaries[&weights.secondary],
+                        self.tertiaries[&weights.tertiary],
+                    ));
+                }
+                FractionalElement::Implicit {
+                    code_point,
+                    secondary,
+                    tertiary,
+                } => {
+                    let (aaaa, bbbb) = table.han_primaries(*code_point).ok_or_else(|| {
+                        SourceLine {
+                            file: &table.path,
+                            line: entry.line,
+                        }
+                        .error(format!("U+{code_point:04X} is not a unified ideograph"))
+                    })?;
+                    packed.push(pack_weights(
+                        false,
+                        aaaa,
+                        secondary
+                            .as_ref()
+                            .map_or(SEC_COMMON, |s| self.secondaries[s]),
+                        tertiary.as_ref().map_or(TER_COMMON, |t| self.tertiaries[t]),
+                    ));
+                    packed.push(pack_weights(false, bbbb, 0, 0));
+                }
+            }
+        }
+
+        Ok(packed.into_boxed_slice())
+    }
+}
+
+fn number_primaries(
+    table: &FractionalTable,
+    primaries: BTreeSet<Box<[u8]>>,
+) -> Result<FxHashMap<Box<[u8]>, u16>> {
+    let first_implicit = table.fixed_byte("first implicit byte")?;
+    let first_trail = table.fixed_byte("first trail byte")?;
+
+    let mut map = FxHashMap::default();
+    let mut next_regular: u16 = 1;
+    let mut next_trailing = FIRST_TRAILING_PRIMARY;
+
+    for primary in primaries {
+        let number = match primary.first() {
+            None => 0,
+            Some(&lead) if lead >= first_trail => {
+                let number = next_trailing;
+                next_trailing = next_trailing
+                    .checked_add(1)
+                    .ok_or_else(|| Error::invalid(&table.path, "too many trailing primaries"))?;
+                number
+            }
+            Some(&lead) if lead >= first_implicit => {
+                return Err(Error::invalid(
+                    &table.path,
+                    format!("explicit primary in the implicit range: {primary:02X?}"),
+                ));
+            }
+            Some(_) => {
+                next_regular += 1;
+                next_regular - 1
+            }
+        };
+
+        map.insert(primary, number);
+    }
+
+    if next_regular > IMPLICIT_FLOOR {
+        return Err(Error::invalid(
+            &table.path,
+            format!("{next_regular} primaries overlap the implicit range"),
+        ));
+    }
+
+    Ok(map)
+}
+
+// Numbers one level so that the common weight gets `common`. Anything that sorts below it is
+// packed in just underneath.
+fn number_level(
+    table: &FractionalTable,
+    level: &str,
+    weights: BTreeSet<Box<[u8]>>,
+    fractional_common: &[u8],
+    common: u16,
+    limit: u16,
+) -> Result<FxHashMap<Box<[u8]>, u16>> {
+    let below = weights
+        .iter()
+        .filter(|w| !w.is_empty() && ***w < *fractional_common)
+        .count();
+    let below = u16::try_from(below).unwrap();
+    if below >= common {
+        return Err(Error::invalid(
+            &table.path,
+            format!("too many {level} weights below the common weight"),
+        ));
+    }
+
+    let mut map = FxHashMap::default();
+    let mut next = common - below;
+    for weight in weights {
+        if weight.is_empty() {
+            map.insert(weight, 0);
+            continue;
+        }
+        if next >= limit {
+            return Err(Error::invalid(
+                &table.path,
+                format!("too many distinct {level} weights"),
+            ));
+        }
+        map.insert(weight, next);
+        next += 1;
+    }
+
+    Ok(map)
+}
+
+/// Mappings from `FractionalUCA` in the shape the trie builder takes
+pub struct FractionalMappings {
+    pub singles: FxHashMap<u32, Box<[u32]>>,
+    pub multis: FxHashMap<Box<[u32]>, Box<[u32]>>,
+    pub prefixes: PrefixMappings,
+}
+
+pub fn collect_fractional(paths: &Paths) -> Result<FractionalMappings> {
+    let table = FractionalTable::read(&paths.fractional_uca())?;
+
+    // The boundary markers are for ICU only
+    let entries: Vec<&FractionalEntry> = table
+        .entries
+        .iter()
+        .filter(|entry| !BOUNDARY_MARKERS.contains(&entry.code_points[0]))
+        .collect();
+
+    let weight_map = FractionalWeightMap::new(&table, &entries)?;
+
+    let mut singles = FxHashMap::default();
+    let mut multis = FxHashMap::default();
+    let mut prefixes = PrefixMappings::default();
+
+    for entry in entries {
+        let weights = weight_map.pack(&table, entry)?;
+        if !entry.prefix.is_empty() {
+            prefixes.insert((entry.prefix.clone(), entry.code_points.clone()), weights);
+        } else if let [code_point] = *entry.code_points {
+            singles.insert(code_point, weights);
+        } else {
+            multis.insert(entry.code_points.clone(), weights);
+        }
+    }
+
+    Ok(FractionalMappings {
+        singles,
+        multis,
+        prefixes,
+    })
+}
+
+pub fn map_fractional(paths: &Paths) -> Result<()> {
+    let mappings = collect_fractional(paths)?;
+
+    // As with the other low tables, this goes only to JSON
+    write_json(
+        &paths.json("low_fractional.json"),
+        &low_from_mappings(&mappings.singles, &mappings.multis).as_slice(),
+    )?;
+
+    let table = build_prefixed_trie_table(&mappings.singles, &mappings.multis, &mappings.prefixes)?;
+    write_table(&paths.out("cldr_root_fractional"), &table)
+}
+
+/// Two mappings whose primaries are ordered one way in `FractionalUCA` and the other way in the
+/// adjusted `allkeys_CLDR.txt` weights
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrderMismatch {
+    pub code_points: Box<[u32]>,
+    pub allkeys_primary: u16,
+    pub fractional_primary: u16,
+    /// A mapping that sorts before this one in allkeys but after it in `FractionalUCA`
+    pub preceded_by: Box<[u32]>,
+    pub preceding_allkeys_primary: u16,
+    pub preceding_fractional_primary: u16,
+}
+
+/// Checks that BUMP and SHIFT keep the primaries of `allkeys_CLDR.txt` in `FractionalUCA` order
+pub fn validate_fractional(paths: &Paths) -> Result<Vec<OrderMismatch>> {
+    let fractional = collect_fractional(paths)?;
+    let singles = collect_singles(paths, Tailoring::default())?;
+    let multis = collect_multis(paths, Tailoring::default())?;
+
+    // Pairs of (allkeys primary, fractional primary, code points), taken element by element from
+    // mappings that have the same number of elements in both tables
+    let mut pairs: Vec<(u16, u16, Box<[u32]>)> = Vec::new();
+    let mut add_pairs = |code_points: Box<[u32]>, allkeys: &[u32], fractional: &[u32]| {
+        if allkeys.len() != fractional.len() {
+            return;
+        }
+        for (&a, &f) in allkeys.iter().zip(fractional) {
+            let (a, f) = (unpack_weights(a).1, unpack_weights(f).1);
+            if a != 0 && f != 0 {
+                pairs.push((a, f, code_points.clone()));
+            }
+        }
+    };
+
+    for (&code_point, weights) in &singles {
+        if let Some(f) = fractional.singles.get(&code_point) {
+            add_pairs(Box::from([code_point]), weights, f);
+        }
+    }
+    for (code_points, weights) in &multis {
+        if let Some(f) = fractional.multis.get(code_points) {
+            add_pairs(code_points.clone(), weights, f);
+        }
+    }
+
+    pairs.sort_unstable();
+
+    // Walk the allkeys order, keeping the pair with the highest fractional primary so far. Any
+    // pair with a higher allkeys primary but a lower fractional one is out of order.
+    let mut mismatches = Vec::new();
+    let mut highest: Option<&(u16, u16, Box<[u32]>)> = None;
+
+    for group in pairs.chunk_by(|x, y| x.0 == y.0) {
+        if let Some((ha, hf, preceding)) = highest {
+            for (a, f, code_points) in group.iter().filter(|(_, f, _)| f < hf) {
+                mismatches.push(OrderMismatch {
+                    code_points: code_points.clone(),
+                    allkeys_primary: *a,
+                    fractional_primary: *f,
+                    preceded_by: preceding.clone(),
+                    preceding_allkeys_primary: *ha,
+                    preceding_fractional_primary: *hf,
+                });
+            }
+        }
+
+        // Groups are sorted by fractional primary, so the last pair is the highest
+        let group_highest = &group[group.len() - 1];
+        if highest.is_none_or(|h| group_highest.1 > h.1) {
+            highest = Some(group_highest);
+        }
+    }
+
+    Ok(mismatches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_lines() {
+        let data = "\
+[UCA version = 16.0.0]
+[Unified_Ideograph 4E00..9FFF FA0E..FA0F FA11]
+[top_byte	2A	Latn ]  # 434 primary weights
+[reorderingTokens	CURRENCY	0D=1 0E=1 ]
+[last variable [0B 8C 64, 05, 05]] # U+1E5FF OL ONAL ABBREVIATION SIGN
+[first tertiary in secondary non-ignorable [X, X, 03]] # U+3041 HIRAGANA LETTER SMALL A
+004C | 00B7; [, FB 94, 05]
+3220; [09 7A, 05, 17][U+4E00, 10][09 7C, 05, 17]
+[fixed first implicit byte E0]
+";
+        let table = FractionalTable::parse(Path::new("FractionalUCA.txt"), data).unwrap();
+
+        assert_eq!(table.uca_version.as_deref(), Some("16.0.0"));
+        assert_eq!(table.unified_ideographs[2], 0xFA11..=0xFA11);
+        assert_eq!(table.top_bytes[0].tokens, ["Latn"]);
+        assert_eq!(table.reordering_tokens[0].leads, [(0x0D, 1), (0x0E, 1)]);
+        assert_eq!(
+            *table.boundary("last variable").unwrap().primary,
+            [0x0B, 0x8C, 0x64]
+        );
+        assert!(table.boundaries[1].weights.primary.is_empty());
+        assert_eq!(table.fixed_byte("first implicit byte").unwrap(), 0xE0);
+
+        assert_eq!(*table.entries[0].prefix, [0x4C]);
+        assert_eq!(*table.entries[0].code_points, [0xB7]);
+        assert_eq!(
+            table.entries[1].elements[1],
+            FractionalElement::Implicit {
+                code_point: 0x4E00,
+                secondary: None,
+                tertiary: Some(Box::from([0x10])),
+            }
+        );
+        assert_eq!(table.han_primaries(0xFA0E), Some((0xFB41, 0xFA0E)));
+        assert_eq!(table.han_primaries(0xFA10), None);
+    }
+
+    #[test]
+    fn keeps_prefix_mappings() {
+        let mappings = collect_fractional(&Paths::default()).unwrap();
+        let key = (Box::from([0x6C]), Box::from([0xB7]));
+
+        assert_eq!(mappings.prefixes.len(), 4);
+        assert_eq!(unpack_weights(mappings.prefixes[&key][0]).1, 0);
+        assert!(mappings.singles.contains_key(&0xB7));
+    }
+}
@@ -0,0 +1,201 @@
+#![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
+
+use crate::{
+    allkeys::CollationElementTable,
+    collation::pack_weights,
+    error::{Result, SourceLine},
+    paths::{Paths, read_input, write_json, write_table},
+};
+use feruca::Tailoring;
+use serde::{Deserialize, Serialize};
+
+// Implicit bases for Han and for unassigned code points (UTS #10, section 10.1). Unlike the bases
+// for Tangut, Nushu and Khitan, these don't appear in allkeys.txt.
+pub const HAN_CORE_BASE: u16 = 0xFB40;
+pub const HAN_OTHER_BASE: u16 = 0xFB80;
+const UNASSIGNED_BASE: u16 = 0xFBC0;
+
+/// Primaries for code points that have no mapping of their own
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ImplicitWeightsTable {
+    /// Sorted and non-overlapping
+    pub ranges: Box<[ImplicitRange]>,
+    /// Base primary for any code point that is neither mapped nor in one of the ranges
+    pub unassigned_base: u16,
+}
+
+/// A run of assigned code points whose implicit primaries are computed from one base.
+///
+/// The first primary is `base + ((cp - origin) >> 15)`, and the second is
+/// `((cp - origin) & 0x7FFF) | 0x8000`. For Tangut, Nushu and Khitan the origin is the start of
+/// the `@implicitweights` range; for Han it's zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ImplicitRange {
+    pub first: u32,
+    pub last: u32,
+    pub origin: u32,
+    pub base: u16,
+}
+
+impl ImplicitWeightsTable {
+    /// The two primaries of the implicit weights for a code point
+    #[must_use]
+    pub fn primaries(&self, code_point: u32) -> (u16, u16) {
+        let i = self.ranges.partition_point(|r| r.last < code_point);
+        let (origin, base) = match self.ranges.get(i) {
+            Some(r) if r.first <= code_point => (r.origin, r.base),
+            _ => (0, self.unassigned_base),
+        };
+
+        implicit_primaries(base, code_point - origin)
+    }
+
+    /// The two packed collation elements for a code point, i.e.,
+    /// `[.AAAA.0020.0002][.BBBB.0000.0000]`
+    #[must_use]
+    pub fn weights(&self, code_point: u32) -> [u32; 2] {
+        pack_implicit(self.primaries(code_point))
+    }
+}
+
+#[must_use]
+pub fn implicit_primaries(base: u16, offset: u32) -> (u16, u16) {
+    (
+        base + u16::try_from(offset >> 15).unwrap(),
+        u16::try_from(offset & 0x7FFF).unwrap() | 0x8000,
+    )
+}
+
+#[must_use]
+pub const fn pack_implicit((aaaa, bbbb): (u16, u16)) -> [u32; 2] {
+    [
+        pack_weights(false, aaaa, 0x20, 0x2),
+        pack_weights(false, bbbb, 0, 0),
+    ]
+}
+
+pub fn map_implicit(paths: &Paths) -> Result<()> {
+    let table = build_implicit_table(paths)?;
+
+    // Write to JSON for debugging
+    write_json(&paths.json("implicit.json"), &table)?;
+
+    write_table(&paths.out("implicit"), &table)
+}
+
+pub fn build_implicit_table(paths: &Paths) -> Result<ImplicitWeightsTable> {
+    // Only DUCET has the @implicitweights lines; CLDR root uses the same implicit weights
+    let keys = CollationElementTable::read(&paths.allkeys(Tailoring::Ducet))?;
+    let assigned = collect_assigned(paths)?;
+
+    let mut ranges: Vec<ImplicitRange> = Vec::new();
+
+    for implicit in &keys.implicit_weights {
+        for run in &assigned {
+            let first = run.first.max(*implicit.range.start());
+            let last = run.last.min(*implicit.range.end());
+            if first <= last {
+                ranges.push(ImplicitRange {
+                    first,
+                    last,
+                    origin: *implicit.range.start(),
+                    base: implicit.base,
+                });
+            }
+        }
+    }
+
+    for run in &assigned {
+        let base = match run.range_name.as_deref() {
+            Some("CJK Ideograph") => HAN_CORE_BASE,
+            Some(name) if name.starts_with("CJK Ideograph Extension") => HAN_OTHER_BASE,
+            _ => continue,
+        };
+
+        ranges.push(ImplicitRange {
+            first: run.first,
+            last: run.last,
+            origin: 0,
+            base,
+        });
+    }
+
+    ranges.sort_unstable_by_key(|r| r.first);
+
+    // Join runs that were split only by the way UnicodeData lists them
+    let mut merged: Vec<ImplicitRange> = Vec::with_capacity(ranges.len());
+    for r in ranges {
+        match merged.last_mut() {
+            Some(prev)
+                if prev.last + 1 == r.first && (prev.origin, prev.base) == (r.origin, r.base) =>
+            {
+                prev.last = r.last;
+            }
+            _ => merged.push(r),
+        }
+    }
+
+    Ok(ImplicitWeightsTable {
+        ranges: merged.into_boxed_slice(),
+        unassigned_base: UNASSIGNED_BASE,
+    })
+}
+
+struct AssignedRun {
+    first: u32,
+    last: u32,
+    // E.g. "CJK Ideograph Extension A", for runs given as <..., First> and <..., Last>
+    range_name: Option<String>,
+}
+
+// Collects the assigned code points in UnicodeData.txt as runs of consecutive code points
+fn collect_assigned(paths: &Paths) -> Result<Vec<AssignedRun>> {
+    let path = paths.unicode_data();
+    let uni_data = read_input(&path)?;
+
+    let mut runs: Vec<AssignedRun> = Vec::new();
+    let mut open_range: Option<(u32, String)> = None;
+
+    for (i, line) in uni_data.lines().enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+
+        let source = SourceLine::new(&path, i);
+        let mut fields = line.split(';');
+        let (Some(code_point), Some(name)) = (fields.next(), fields.next()) else {
+            return Err(source.error("expected at least two fields"));
+        };
+        let code_point = source.hex_u32(code_point)?;
+
+        if let Some(range_name) = name.strip_suffix(", First>") {
+            open_range = Some((code_point, range_name.trim_start_matches('<').to_string()));
+            continue;
+        }
+
+        if name.ends_with(", Last>") {
+            let (first, range_name) = open_range
+                .take()
+                .ok_or_else(|| source.error("range end without a start"))?;
+            runs.push(AssignedRun {
+                first,
+                last: code_point,
+                range_name: Some(range_name),
+            });
+            continue;
+        }
+
+        match runs.last_mut() {
+            Some(run) if run.range_name.is_none() && run.last + 1 == code_point => {
+                run.last = code_point;
+            }
+            _ => runs.push(AssignedRun {
+                first: code_point,
+                last: code_point,
+                range_name: None,
+            }),
+        }
+    }
+
+    Ok(runs)
+}
@@ -0,0 +1,124 @@
+#![allow(clippy::missing_errors_doc)]
+
+use crate::{
+    collation::{collect_low, collect_multis, collect_singles, unpack_weights},
+    error::{Error, Result},
+    normalization::{build_decomp_table, collect_decomps, collect_fcd},
+    paths::{Paths, write_output},
+    variable::collect_variable,
+    version::LegacyLayout,
+};
+use feruca::Tailoring;
+use rustc_hash::FxHashMap;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::BuildHasher,
+    path::Path,
+};
+
+// The unpacked form of a set of weights, as serialized for Unicode 14
+#[derive(Serialize)]
+struct Weights {
+    variable: bool,
+    primary: u16,
+    secondary: u16,
+    tertiary: u16,
+}
+
+impl From<u32> for Weights {
+    fn from(packed: u32) -> Self {
+        let (variable, primary, secondary, tertiary) = unpack_weights(packed);
+        Self {
+            variable,
+            primary,
+            secondary,
+            tertiary,
+        }
+    }
+}
+
+/// Writes the map-based tables that feruca used before the trie layout.
+///
+/// These are `decomp`, `fcd`, `variable`, `low`, `low_cldr`, `singles`, `singles_cldr`, `multis`
+/// and `multis_cldr`. Nothing is written for a data version without a legacy layout.
+pub fn map_legacy(paths: &Paths) -> Result<()> {
+    let Some(layout) = paths.version.legacy_layout() else {
+        return Ok(());
+    };
+
+    let decomps = collect_decomps(paths)?;
+    write_bincode(&paths.legacy("decomp"), &decomps)?;
+
+    // Unlike the FCD table, the legacy map keeps entries whose combining classes are both zero
+    let fcd = collect_fcd(paths, &build_decomp_table(&decomps))?;
+    write_bincode(&paths.legacy("fcd"), &fcd)?;
+
+    write_bincode(&paths.legacy("variable"), &collect_variable(paths)?)?;
+
+    for (keys, suffix) in [(Tailoring::Ducet, ""), (Tailoring::default(), "_cldr")] {
+        let low = collect_low(paths, keys)?;
+        match layout {
+            LegacyLayout::StructWeights => {
+                let low: FxHashMap<u32, Weights> =
+                    low.into_iter().map(|(k, v)| (k, v.into())).collect();
+                write_bincode(&paths.legacy(&format!("low{suffix}")), &low)?;
+            }
+            LegacyLayout::PackedWeights => {
+                write_bincode(&paths.legacy(&format!("low{suffix}")), &low)?;
+            }
+        }
+
+        let singles = collect_singles(paths, keys)?;
+        write_legacy_singles(&paths.legacy(&format!("singles{suffix}")), &singles, layout)?;
+
+        let multis = collect_multis(paths, keys)?;
+        write_legacy_multis(&paths.legacy(&format!("multis{suffix}")), &multis, layout)?;
+    }
+
+    Ok(())
+}
+
+pub fn write_legacy_singles<S: BuildHasher>(
+    path: &Path,
+    singles: &HashMap<u32, Box<[u32]>, S>,
+    layout: LegacyLayout,
+) -> Result<()> {
+    write_weight_map(path, singles.iter().map(|(&k, v)| (k, v)), layout)
+}
+
+pub fn write_legacy_multis<S: BuildHasher>(
+    path: &Path,
+    multis: &HashMap<Box<[u32]>, Box<[u32]>, S>,
+    layout: LegacyLayout,
+) -> Result<()> {
+    write_weight_map(path, multis.iter().map(|(k, v)| (k.as_ref(), v)), layout)
+}
+
+// In key order, so that the bytes don't depend on the hasher; feruca reads them into a hash map
+fn write_weight_map<'a, K>(
+    path: &Path,
+    entries: impl Iterator<Item = (K, &'a Box<[u32]>)>,
+    layout: LegacyLayout,
+) -> Result<()>
+where
+    K: Ord + Serialize,
+{
+    match layout {
+        LegacyLayout::StructWeights => {
+            let map: BTreeMap<K, Vec<Weights>> = entries
+                .map(|(k, v)| (k, v.iter().map(|&w| w.into()).collect()))
+                .collect();
+            write_bincode(path, &map)
+        }
+        LegacyLayout::PackedWeights => {
+            let map: BTreeMap<K, &[u32]> = entries.map(|(k, v)| (k, v.as_ref())).collect();
+            write_bincode(path, &map)
+        }
+    }
+}
+
+fn write_bincode(path: &Path, value: &impl Serialize) -> Result<()> {
+    let bytes = bincode::serialize(value).map_err(|e| Error::serialization(path, e))?;
+    write_output(path, &bytes)
+}
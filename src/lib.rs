@@ -1,28 +1,105 @@
 #![warn(clippy::pedantic, clippy::nursery)]
 
+mod allkeys;
+pub use allkeys::{CollationElement, CollationElementTable, CollationEntry, ImplicitWeights};
+
+mod archive;
+pub use archive::{Archived, map_archives};
+
+mod bundle;
+pub use bundle::{BASE_PAGE, TrieBundle, TrieOverlay, map_bundle, verify_bundle};
+
+mod closure;
+pub use closure::canonical_closure;
+
 mod collation;
 pub use collation::{
-    BUMP, KEYS_CLDR, SHIFT, collect_multis, collect_singles, map_low, pack_code_points,
-    pack_weights, unpack_weights,
+    BUMP, MultiMappings, PrefixKey, PrefixMappings, SHIFT, collect_low, collect_multis,
+    collect_singles, format_weights, low_from_mappings, map_low, pack_weights, unpack_weights,
 };
 
 mod common;
 pub use common::VARIABLE_EMPTY_PAGE;
 
+mod conformance;
+pub use conformance::{ConformanceFailure, ConformanceReport, run_collation_test, run_conformance};
+
+mod delta;
+pub use delta::{DeltaContraction, DeltaEntry, TailoringDelta, write_tailoring};
+
+mod diff;
+pub use diff::{Change, OutputDiff, TableDiff, TableKind, diff_outputs};
+
+mod dump;
+pub use dump::dump_trie;
+
+mod error;
+pub use error::{Error, Result};
+
+mod fractional;
+pub use fractional::{
+    Boundary, FractionalElement, FractionalEntry, FractionalMappings, FractionalTable,
+    FractionalWeightMap, FractionalWeights, OrderMismatch, ReorderingToken, TopByte,
+    collect_fractional, map_fractional, validate_fractional,
+};
+
+mod implicit;
+pub use implicit::{
+    HAN_CORE_BASE, HAN_OTHER_BASE, ImplicitRange, ImplicitWeightsTable, build_implicit_table,
+    implicit_primaries, map_implicit, pack_implicit,
+};
+
+mod legacy;
+pub use legacy::{map_legacy, write_legacy_multis, write_legacy_singles};
+
+mod locales;
+pub use locales::{LOCALE_TAILORINGS, LocaleTailoring, ManifestEntry, map_manifest};
+
+mod names;
+pub use names::CharacterNames;
+
 mod normalization;
 pub use normalization::{
-    DecompTable, FcdTable, build_decomp_table, build_fcd_table, map_decomps, map_fcd,
+    DecompTable, FcdTable, build_decomp_table, build_fcd_table, collect_decomps, collect_fcd,
+    map_decomps, map_fcd,
 };
 
+mod paths;
+pub use paths::{Paths, write_output};
+
+mod remap;
+pub use remap::Tailoring;
+
+mod reorder;
+pub use reorder::{PrimaryReordering, ReorderGroup, collect_reorder_groups, map_reorder_trie};
+
+mod rules;
+pub use rules::{CollationRules, Level, Relation, RuleChain, rules_name};
+
+mod sort_key;
+pub use sort_key::{CollationTables, VariableWeighting};
+
+mod stats;
+pub use stats::{TableStats, collect_stats, stats_report};
+
+mod tailor;
+pub use tailor::{TailoredMappings, apply_rules, map_rules_trie};
+
 mod trie;
 pub use trie::{
     CollationTrieTable, ContractionEdge, ContractionMeta, ENTRY_CONTRACTION, ENTRY_MISSING,
-    ENTRY_SIMPLE, build_trie_table, entry_len, entry_meta_index, entry_start, entry_tag,
-    map_cldr_trie, map_trie, write_trie,
+    ENTRY_SIMPLE, PrefixMapping, build_prefixed_trie_table, build_trie_table, entry_len,
+    entry_meta_index, entry_start, entry_tag, map_cldr_trie, map_trie, write_trie,
 };
 
 mod variable;
-pub use variable::{VariableTable, build_variable_table, map_variable};
+pub use variable::{VariableTable, build_variable_table, collect_variable, map_variable};
+
+mod verify;
+pub use verify::{TrieMismatch, verify_trie, verify_trie_file};
+
+mod version;
+pub use version::{DataVersion, LegacyLayout};
 
 #[macro_export]
 macro_rules! regex {
@@ -0,0 +1,97 @@
+#![allow(clippy::missing_errors_doc)]
+
+use crate::{
+    error::Result,
+    paths::{Paths, write_json},
+    remap::Tailoring,
+};
+use serde::Serialize;
+
+/// A tailoring built from the CLDR rules in `collation/<name>.txt`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LocaleTailoring {
+    /// File name of the rules and of the trie under `tailoring/`
+    pub name: &'static str,
+    /// BCP 47 locale ID, e.g. `de-u-co-phonebk`
+    pub locale: &'static str,
+}
+
+/// The locale tailorings that `all` builds, where the data directory has rules for them
+pub const LOCALE_TAILORINGS: [LocaleTailoring; 11] = [
+    LocaleTailoring {
+        name: "cs",
+        locale: "cs",
+    },
+    LocaleTailoring {
+        name: "da",
+        locale: "da",
+    },
+    LocaleTailoring {
+        name: "de-phonebook",
+        locale: "de-u-co-phonebk",
+    },
+    LocaleTailoring {
+        name: "es",
+        locale: "es",
+    },
+    LocaleTailoring {
+        name: "es-traditional",
+        locale: "es-u-co-trad",
+    },
+    LocaleTailoring {
+        name: "fi",
+        locale: "fi",
+    },
+    LocaleTailoring {
+        name: "hr",
+        locale: "hr",
+    },
+    LocaleTailoring {
+        name: "nb",
+        locale: "nb",
+    },
+    LocaleTailoring {
+        name: "pl",
+        locale: "pl",
+    },
+    LocaleTailoring {
+        name: "sv",
+        locale: "sv",
+    },
+    LocaleTailoring {
+        name: "tr",
+        locale: "tr",
+    },
+];
+
+/// An entry in `tailoring/manifest.json`
+#[derive(Debug, Serialize)]
+pub struct ManifestEntry {
+    pub name: String,
+    pub locale: Option<String>,
+}
+
+/// Lists the tailorings in the output directory, in `tailoring/manifest.json`
+///
+/// The tailorings declared in `tailorings.json` come first, by name; they have no locale ID.
+pub fn map_manifest(paths: &Paths) -> Result<()> {
+    let mut custom: Vec<String> = (Tailoring::read_config(paths)?.iter())
+        .map(|t| t.name().to_string())
+        .collect();
+    custom.sort_unstable();
+
+    let custom = custom
+        .into_iter()
+        .map(|name| ManifestEntry { name, locale: None });
+    let locales = LOCALE_TAILORINGS.iter().map(|t| ManifestEntry {
+        name: t.name.to_string(),
+        locale: Some(t.locale.to_string()),
+    });
+
+    let manifest: Vec<ManifestEntry> = custom
+        .chain(locales)
+        .filter(|entry| paths.out(&format!("tailoring/{}", entry.name)).is_file())
+        .collect();
+
+    write_json(&paths.out("tailoring/manifest.json"), &manifest)
+}
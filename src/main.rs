@@ -2,42 +2,424 @@
 
 use feruca::Tailoring;
 use feruca_mapper::{
-    collect_multis, collect_singles, map_cldr_trie, map_decomps, map_fcd, map_low, map_trie,
-    map_variable,
+    CharacterNames, CollationTables, CollationTrieTable, DataVersion, Error, LOCALE_TAILORINGS,
+    Paths, PrefixMappings, Result, TailoringDelta, collect_multis, collect_singles, collect_stats,
+    diff_outputs, dump_trie, map_archives, map_bundle, map_cldr_trie, map_decomps, map_fcd,
+    map_fractional, map_implicit, map_legacy, map_low, map_manifest, map_reorder_trie,
+    map_rules_trie, map_trie, map_variable, run_conformance, stats_report, validate_fractional,
+    verify_bundle, verify_trie_file,
 };
+use rustc_hash::FxHashMap;
 
-mod arabic_script;
-use arabic_script::map_arabic_script_trie;
+mod cli;
+use cli::{Args, Command, DiffQuery, KeyQuery, TrieTarget, USAGE, parse_args};
 
-mod arabic_interleaved;
-use arabic_interleaved::map_arabic_interleaved_trie;
+fn main() {
+    let Args {
+        command,
+        paths,
+        verify,
+    } = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("error: {message}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
 
-mod arabic_tailoring;
+    if command == Command::Legacy && paths.version.legacy_layout().is_none() {
+        eprintln!("error: {} has no legacy layout", paths.version);
+        std::process::exit(2);
+    }
 
-fn main() {
-    timed("Decompositions", map_decomps);
-    timed("FCD", map_fcd);
-    timed("Variable table", map_variable);
-    timed("Low mappings (DUCET)", || map_low(Tailoring::Ducet));
-    timed("Low mappings (CLDR)", || map_low(Tailoring::default()));
-    timed("Trie mappings (DUCET)", || map_trie(Tailoring::Ducet));
+    if let Err(e) = run(command, &paths, verify) {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn run(command: Command, paths: &Paths, verify: bool) -> Result<()> {
+    match command {
+        Command::All => {
+            timed("Decompositions", || map_decomps(paths))?;
+            timed("FCD", || map_fcd(paths))?;
+            timed("Variable table", || map_variable(paths))?;
+            timed("Implicit weights", || map_implicit(paths))?;
+            run_low(paths)?;
+            run_tries(paths, &TrieTarget::All, verify)?;
+            run_bundle(paths, verify)?;
+
+            if paths.version.legacy_layout().is_some() {
+                run_legacy(paths)?;
+            }
+            if paths.fractional_uca().is_file() {
+                run_fractional(paths)?;
+            }
+        }
+        Command::Decomp => timed("Decompositions", || map_decomps(paths))?,
+        Command::Fcd => timed("FCD", || map_fcd(paths))?,
+        Command::Variable => timed("Variable table", || map_variable(paths))?,
+        Command::Implicit => timed("Implicit weights", || map_implicit(paths))?,
+        Command::Low => run_low(paths)?,
+        Command::Trie(target) => run_tries(paths, &target, verify)?,
+        Command::Bundle => run_bundle(paths, verify)?,
+        Command::Archive => timed("Archived tables", || map_archives(paths, verify))?,
+        Command::Legacy => run_legacy(paths)?,
+        Command::Fractional => run_fractional(paths)?,
+        Command::Reorder(codes) => run_reorder(paths, &codes)?,
+        Command::Rules(names) => run_rules(paths, &names, verify)?,
+        Command::Conformance => run_conformance_tests(paths)?,
+        Command::Key(query) => run_key(paths, &query)?,
+        Command::Dump(names) => run_dump(paths, &names)?,
+        Command::Stats => print!("{}", stats_report(&collect_stats(&paths.out_dir)?)),
+        Command::Diff(query) => run_diff(paths, &query)?,
+        Command::Help => println!("{USAGE}"),
+    }
+
+    Ok(())
+}
+
+fn run_low(paths: &Paths) -> Result<()> {
+    timed("Low mappings (DUCET)", || map_low(paths, Tailoring::Ducet))?;
+    timed("Low mappings (CLDR)", || {
+        map_low(paths, Tailoring::default())
+    })
+}
 
+fn run_tries(paths: &Paths, target: &TrieTarget, verify: bool) -> Result<()> {
+    if matches!(target, TrieTarget::All | TrieTarget::Ducet) {
+        timed("Trie mappings (DUCET)", || {
+            map_trie(paths, Tailoring::Ducet)
+        })?;
+
+        if verify {
+            let singles = collect_singles(paths, Tailoring::Ducet)?;
+            let multis = collect_multis(paths, Tailoring::Ducet)?;
+            verify_trie(paths, "ducet", &singles, &multis)?;
+        }
+    }
+
+    if *target == TrieTarget::Ducet {
+        return Ok(());
+    }
+
+    let tailorings = feruca_mapper::Tailoring::read_config(paths)?;
+    if let TrieTarget::Named(name) = target
+        && !tailorings.iter().any(|t| t.name() == name)
+    {
+        return Err(Error::Invalid {
+            file: paths.tailorings(),
+            message: format!("no tailoring named {name}"),
+        });
+    }
+
+    // Every other target is built on the CLDR root mappings
     let cldr_singles = timed("Collect mappings (CLDR singles)", || {
-        collect_singles(Tailoring::default())
-    });
+        collect_singles(paths, Tailoring::default())
+    })?;
     let cldr_multis = timed("Collect mappings (CLDR multis)", || {
-        collect_multis(Tailoring::default())
-    });
-
-    timed("Trie mappings (CLDR)", || {
-        map_cldr_trie(&cldr_singles, &cldr_multis);
-    });
-    timed("Trie mappings (ArabicScript)", || {
-        map_arabic_script_trie(&cldr_singles, &cldr_multis);
-    });
-    timed("Trie mappings (ArabicInterleaved)", || {
-        map_arabic_interleaved_trie(&cldr_singles, &cldr_multis);
-    });
+        collect_multis(paths, Tailoring::default())
+    })?;
+
+    if matches!(target, TrieTarget::All | TrieTarget::Cldr) {
+        timed("Trie mappings (CLDR)", || {
+            map_cldr_trie(paths, &cldr_singles, &cldr_multis)
+        })?;
+
+        if verify {
+            verify_trie(paths, "cldr_root", &cldr_singles, &cldr_multis)?;
+        }
+    }
+    for tailoring in &tailorings {
+        let name = tailoring.name();
+        let selected = match target {
+            TrieTarget::All | TrieTarget::Custom => true,
+            TrieTarget::Named(target) => target == name,
+            _ => false,
+        };
+        if !selected {
+            continue;
+        }
+
+        let mappings = timed(&format!("Trie mappings ({name})"), || {
+            tailoring.map_trie(paths, &cldr_singles, &cldr_multis)
+        })?;
+
+        if verify {
+            verify_trie(
+                paths,
+                &format!("tailoring/{name}"),
+                &mappings.singles,
+                &mappings.multis,
+            )?;
+        }
+    }
+    if matches!(target, TrieTarget::All | TrieTarget::Locales) {
+        for tailoring in &LOCALE_TAILORINGS {
+            if paths.collation_rules(tailoring.name).is_file() {
+                run_rules_trie(paths, tailoring.name, &cldr_singles, &cldr_multis, verify)?;
+            }
+        }
+    }
+
+    map_manifest(paths)
+}
+
+fn run_bundle(paths: &Paths, verify: bool) -> Result<()> {
+    let bundle = timed("Trie bundle", || map_bundle(paths))?;
+
+    if verify {
+        let mismatches = timed("Verify trie bundle", || verify_bundle(paths, &bundle))?;
+        if !mismatches.is_empty() {
+            return Err(Error::Invalid {
+                file: paths.out("bundle"),
+                message: format!(
+                    "overlays don't match their tries: {}",
+                    mismatches.join(", ")
+                ),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn run_reorder(paths: &Paths, codes: &[String]) -> Result<()> {
+    let cldr_singles = collect_singles(paths, Tailoring::default())?;
+    let cldr_multis = collect_multis(paths, Tailoring::default())?;
+
+    timed("Trie mappings (reordered)", || {
+        map_reorder_trie(paths, codes, &cldr_singles, &cldr_multis)
+    })
+}
+
+fn run_rules(paths: &Paths, names: &[String], verify: bool) -> Result<()> {
+    let cldr_singles = collect_singles(paths, Tailoring::default())?;
+    let cldr_multis = collect_multis(paths, Tailoring::default())?;
+
+    for name in names {
+        run_rules_trie(paths, name, &cldr_singles, &cldr_multis, verify)?;
+    }
+
+    map_manifest(paths)
+}
+
+fn run_rules_trie(
+    paths: &Paths,
+    name: &str,
+    cldr_singles: &FxHashMap<u32, Box<[u32]>>,
+    cldr_multis: &FxHashMap<Box<[u32]>, Box<[u32]>>,
+    verify: bool,
+) -> Result<()> {
+    let mappings = timed(&format!("Trie mappings ({name})"), || {
+        map_rules_trie(paths, name, cldr_singles, cldr_multis)
+    })?;
+
+    if verify {
+        verify_trie(
+            paths,
+            &format!("tailoring/{name}"),
+            &mappings.singles,
+            &mappings.multis,
+        )?;
+
+        let path = paths.out(&format!("tailoring/{name}"));
+        let table = CollationTrieTable::read(&path)?;
+        let prefixes: PrefixMappings = table
+            .prefix_mappings()
+            .map(|(prefix, code_points, weights)| {
+                ((prefix.into(), code_points.into()), weights.into())
+            })
+            .collect();
+        if prefixes != mappings.prefixes {
+            return Err(Error::Invalid {
+                file: path,
+                message: "the trie doesn't hold the tailored prefix mappings".to_string(),
+            });
+        }
+
+        let path = paths.out(&format!("delta/{name}"));
+        let delta = TailoringDelta::read(&path)?.apply(cldr_singles, cldr_multis);
+        if delta.singles != mappings.singles
+            || delta.multis != mappings.multis
+            || delta.prefixes != mappings.prefixes
+        {
+            return Err(Error::Invalid {
+                file: path,
+                message: "the delta doesn't reproduce the tailored mappings".to_string(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// Reads back a trie from the output directory, reporting any mappings that it lost
+fn verify_trie(
+    paths: &Paths,
+    name: &str,
+    singles: &FxHashMap<u32, Box<[u32]>>,
+    multis: &FxHashMap<Box<[u32]>, Box<[u32]>>,
+) -> Result<()> {
+    let path = paths.out(name);
+    let mismatches = timed(&format!("Verify trie ({name})"), || {
+        verify_trie_file(&path, singles, multis)
+    })?;
+
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+
+    for m in mismatches.iter().take(20) {
+        eprintln!("mismatch: {m}");
+    }
+    Err(Error::Invalid {
+        file: path,
+        message: format!("{} mappings don't match the trie", mismatches.len()),
+    })
+}
+
+fn run_conformance_tests(paths: &Paths) -> Result<()> {
+    let mut failed = 0;
+
+    for keys in [Tailoring::Ducet, Tailoring::default()] {
+        for report in timed("Conformance", || run_conformance(paths, keys))? {
+            println!(
+                "{}: {} lines checked, {} skipped, {} out of order",
+                report.path.display(),
+                report.checked,
+                report.skipped,
+                report.failures.len()
+            );
+            for failure in report.failures.iter().take(20) {
+                eprintln!("{failure}");
+            }
+            failed += report.failures.len();
+        }
+    }
+
+    if failed > 0 {
+        return Err(Error::Invalid {
+            file: paths.data_dir.clone(),
+            message: format!("{failed} conformance test lines out of order"),
+        });
+    }
+
+    Ok(())
+}
+
+fn run_key(paths: &Paths, query: &KeyQuery) -> Result<()> {
+    let tables = match query.table.as_str() {
+        "ducet" => CollationTables::read(paths, Tailoring::Ducet)?,
+        "cldr" => CollationTables::read(paths, Tailoring::default())?,
+        name => CollationTables::read_tailoring(paths, name)?,
+    };
+
+    for text in &query.texts {
+        let chars: Vec<char> = text.chars().collect();
+        let key = tables.sort_key(&chars, query.weighting);
+        let levels: Vec<String> = key
+            .split(|&weight| weight == 0)
+            .map(|level| {
+                let hex: Vec<String> = level.iter().map(|w| format!("{w:04X}")).collect();
+                hex.join(" ")
+            })
+            .collect();
+
+        println!("{text}: [{}]", levels.join(" | "));
+    }
+
+    Ok(())
+}
+
+fn run_dump(paths: &Paths, names: &[String]) -> Result<()> {
+    let character_names = CharacterNames::read(paths)?;
+
+    for name in names {
+        let output = match name.as_str() {
+            "ducet" => "ducet".to_string(),
+            "cldr" => "cldr_root".to_string(),
+            name => format!("tailoring/{name}"),
+        };
+        let table = CollationTrieTable::read(&paths.out(&output))?;
+
+        println!("# {output}");
+        print!("{}", dump_trie(&table, &character_names));
+    }
+
+    Ok(())
+}
+
+fn run_diff(paths: &Paths, query: &DiffQuery) -> Result<()> {
+    // A data version stands for its default output directory, and names come from its own
+    // UnicodeData.txt; a bare directory takes them from the data directory in use
+    let resolve = |output: &str| {
+        output.parse::<DataVersion>().map_or_else(
+            |_| Paths {
+                out_dir: output.into(),
+                ..paths.clone()
+            },
+            Paths::for_version,
+        )
+    };
+    let old = resolve(&query.outputs[0]);
+    let new = resolve(&query.outputs[1]);
+
+    let diff = diff_outputs(&old.out_dir, &new.out_dir)?;
+
+    if query.json {
+        println!("{}", diff.to_json()?);
+        return Ok(());
+    }
+
+    let mut names = CharacterNames::read(&new)?;
+    if old.data_dir != new.data_dir {
+        names.extend(CharacterNames::read(&old)?);
+    }
+    print!("{}", diff.report(&names));
+
+    Ok(())
+}
+
+fn run_legacy(paths: &Paths) -> Result<()> {
+    timed("Legacy tables", || map_legacy(paths))?;
+
+    for tailoring in feruca_mapper::Tailoring::read_config(paths)? {
+        timed(&format!("Legacy tables ({})", tailoring.name()), || {
+            tailoring.map_legacy(paths)
+        })?;
+    }
+
+    Ok(())
+}
+
+fn run_fractional(paths: &Paths) -> Result<()> {
+    timed("Trie mappings (FractionalUCA)", || map_fractional(paths))?;
+    let mismatches = timed("Validate BUMP/SHIFT against FractionalUCA", || {
+        validate_fractional(paths)
+    })?;
+
+    for m in mismatches.iter().take(20) {
+        eprintln!(
+            "out of order: {} ({:04X} in allkeys, {:04X} fractional) after {} ({:04X}, {:04X})",
+            format_code_points(&m.code_points),
+            m.allkeys_primary,
+            m.fractional_primary,
+            format_code_points(&m.preceded_by),
+            m.preceding_allkeys_primary,
+            m.preceding_fractional_primary,
+        );
+    }
+    if !mismatches.is_empty() {
+        eprintln!("{} primaries out of FractionalUCA order", mismatches.len());
+    }
+
+    Ok(())
+}
+
+fn format_code_points(code_points: &[u32]) -> String {
+    let hex: Vec<String> = code_points.iter().map(|cp| format!("{cp:04X}")).collect();
+    hex.join(" ")
 }
 
 fn timed<T>(label: &str, f: impl FnOnce() -> T) -> T {
@@ -0,0 +1,91 @@
+#![allow(clippy::missing_errors_doc)]
+
+use crate::{
+    error::{Result, SourceLine},
+    paths::{Paths, read_input},
+};
+use rustc_hash::FxHashMap;
+
+/// Character names from `UnicodeData.txt`, for reports
+#[derive(Debug, Default)]
+pub struct CharacterNames {
+    names: FxHashMap<u32, String>,
+    // Runs given as <..., First> and <..., Last>, e.g. "CJK Ideograph Extension A"
+    ranges: Vec<(u32, u32, String)>,
+}
+
+impl CharacterNames {
+    pub fn read(paths: &Paths) -> Result<Self> {
+        let path = paths.unicode_data();
+        let uni_data = read_input(&path)?;
+
+        let mut names = Self::default();
+        let mut open_range: Option<u32> = None;
+
+        for (i, line) in uni_data.lines().enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+
+            let source = SourceLine::new(&path, i);
+            let fields: Vec<&str> = line.split(';').collect();
+            if fields.len() < 11 {
+                return Err(source.error("expected at least eleven fields"));
+            }
+            let code_point = source.hex_u32(fields[0])?;
+            let name = fields[1];
+
+            if name.ends_with(", First>") {
+                open_range = Some(code_point);
+            } else if let Some(range_name) = name.strip_suffix(", Last>") {
+                let first = open_range
+                    .take()
+                    .ok_or_else(|| source.error("range end without a start"))?;
+                let range_name = range_name.trim_start_matches('<').to_uppercase();
+                names.ranges.push((first, code_point, range_name));
+            } else if name == "<control>" {
+                // The Unicode 1.0 name is more useful, where there is one
+                let name = if fields[10].is_empty() {
+                    name
+                } else {
+                    fields[10]
+                };
+                names.names.insert(code_point, name.to_string());
+            } else {
+                names.names.insert(code_point, name.to_string());
+            }
+        }
+
+        Ok(names)
+    }
+
+    /// Fills in names that this set lacks from another, e.g. an older release
+    pub fn extend(&mut self, other: Self) {
+        for (code_point, name) in other.names {
+            self.names.entry(code_point).or_insert(name);
+        }
+        self.ranges.extend(other.ranges);
+    }
+
+    #[must_use]
+    pub fn get(&self, code_point: u32) -> Option<&str> {
+        self.names.get(&code_point).map(String::as_str).or_else(|| {
+            self.ranges
+                .iter()
+                .find(|(first, last, _)| (*first..=*last).contains(&code_point))
+                .map(|(_, _, name)| name.as_str())
+        })
+    }
+
+    /// E.g. `U+004C U+00B7 (LATIN CAPITAL LETTER L, MIDDLE DOT)`
+    #[must_use]
+    pub fn describe(&self, code_points: &[u32]) -> String {
+        let hex: Vec<String> = code_points.iter().map(|cp| format!("U+{cp:04X}")).collect();
+        let names: Vec<&str> = code_points
+            .iter()
+            .map(|&cp| self.get(cp).unwrap_or("<unassigned>"))
+            .collect();
+
+        format!("{} ({})", hex.join(" "), names.join(", "))
+    }
+}
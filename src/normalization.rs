@@ -1,15 +1,18 @@
-#![allow(clippy::missing_panics_doc, clippy::regex_creation_in_loops)]
+#![allow(
+    clippy::missing_errors_doc,
+    clippy::missing_panics_doc,
+    clippy::regex_creation_in_loops
+)]
 
 use crate::common::{CODE_POINT_COUNT, PAGE_SIZE, VARIABLE_EMPTY_PAGE};
+use crate::error::{Result, SourceLine};
+use crate::paths::{Paths, read_input, read_table, write_json, write_table};
 use crate::regex;
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, hash::BuildHasher, ops::RangeInclusive, sync::LazyLock};
+use std::{collections::HashMap, hash::BuildHasher, ops::RangeInclusive};
 use unicode_canonical_combining_class::get_canonical_combining_class_u32 as get_ccc;
 
-static UNI_DATA: LazyLock<String> =
-    LazyLock::new(|| std::fs::read_to_string("unicode-data/cldr-46_1/UnicodeData.txt").unwrap());
-
 // Ignored code point ranges for decompositions and FCD
 const IGNORED_RANGES: [RangeInclusive<u32>; 15] = [
     0x3400..=0x4DBF,
@@ -29,13 +32,7 @@ const IGNORED_RANGES: [RangeInclusive<u32>; 15] = [
     0x10_0000..=0x10_FFFD,
 ];
 
-// The output of map_decomps is needed for map_fcd
-static DECOMP: LazyLock<DecompTable> = LazyLock::new(|| {
-    let data = std::fs::read("bincode/cldr-46_1/decomp").unwrap();
-    postcard::from_bytes(&data).unwrap()
-});
-
-#[derive(Serialize)]
+#[derive(Deserialize, Serialize)]
 pub struct FcdTable {
     pub page_index: Box<[u16]>,
     pub pages: Box<[u16]>,
@@ -48,6 +45,21 @@ pub struct DecompTable {
     pub values: Box<[u32]>,
 }
 
+impl FcdTable {
+    /// The combining classes of the first and last code points of a decomposition, packed as in
+    /// `collect_fcd`; zero for code points that don't decompose
+    #[must_use]
+    pub fn get(&self, code_point: u32) -> u16 {
+        let page = self.page_index[usize::try_from(code_point >> 8).unwrap()];
+        if page == VARIABLE_EMPTY_PAGE {
+            return 0;
+        }
+
+        let offset = usize::try_from(code_point & 0xFF).unwrap();
+        self.pages[(usize::from(page) << 8) + offset]
+    }
+}
+
 impl DecompTable {
     #[must_use]
     pub fn get(&self, code_point: u32) -> Option<&[u32]> {
@@ -68,19 +80,43 @@ impl DecompTable {
     }
 }
 
-pub fn map_decomps() {
+pub fn map_decomps(paths: &Paths) -> Result<()> {
+    let canonical = collect_decomps(paths)?;
+
+    let mut sorted: Vec<(u32, Box<[u32]>)> = canonical
+        .iter()
+        .map(|(&code_point, decomp)| (code_point, decomp.clone()))
+        .collect();
+    sorted.sort_unstable_by_key(|&(code_point, _)| code_point);
+
+    // Write to JSON for debugging
+    write_json(&paths.json("decomp.json"), &sorted)?;
+
+    // Write to bincode; this is what we actually use
+    let table = build_decomp_table(&canonical);
+    write_table(&paths.out("decomp"), &table)
+}
+
+pub fn collect_decomps(paths: &Paths) -> Result<FxHashMap<u32, Box<[u32]>>> {
+    let path = paths.unicode_data();
+    let uni_data = read_input(&path)?;
+
     let mut listed: FxHashMap<u32, Vec<u32>> = FxHashMap::default();
     let mut canonical: FxHashMap<u32, Box<[u32]>> = FxHashMap::default();
 
     // First pass: collect listed decompositions
-    for line in UNI_DATA.lines() {
+    for (i, line) in uni_data.lines().enumerate() {
         if line.is_empty() {
             continue;
         }
 
+        let source = SourceLine::new(&path, i);
         let fields: Vec<&str> = line.split(';').collect();
+        if fields.len() < 6 {
+            return Err(source.error("expected at least six fields"));
+        }
 
-        let code_point = u32::from_str_radix(fields[0], 16).unwrap();
+        let code_point = source.hex_u32(fields[0])?;
         if IGNORED_RANGES.iter().any(|r| r.contains(&code_point)) {
             continue;
         }
@@ -98,11 +134,13 @@ pub fn map_decomps() {
 
         let mut decomp: Vec<u32> = Vec::new();
         for m in re.find_iter(decomp_col) {
-            let code_point = u32::from_str_radix(m.as_str(), 16).unwrap();
+            let code_point = source.hex_u32(m.as_str())?;
             decomp.push(code_point);
         }
 
-        assert!(!decomp.is_empty());
+        if decomp.is_empty() {
+            return Err(source.error(format!("malformed decomposition: {decomp_col}")));
+        }
 
         listed.insert(code_point, decomp);
     }
@@ -123,20 +161,7 @@ pub fn map_decomps() {
         canonical.insert(*code_point, final_decomp);
     }
 
-    let mut sorted: Vec<(u32, Box<[u32]>)> = canonical
-        .iter()
-        .map(|(&code_point, decomp)| (code_point, decomp.clone()))
-        .collect();
-    sorted.sort_unstable_by_key(|&(code_point, _)| code_point);
-
-    // Write to JSON for debugging
-    let json_bytes = serde_json::to_vec(&sorted).unwrap();
-    std::fs::write("json/cldr-46_1/decomp.json", json_bytes).unwrap();
-
-    // Write to bincode; this is what we actually use
-    let table = build_decomp_table(&canonical);
-    let bytes = postcard::to_allocvec(&table).unwrap();
-    std::fs::write("bincode/cldr-46_1/decomp", bytes).unwrap();
+    Ok(canonical)
 }
 
 #[must_use]
@@ -206,22 +231,48 @@ fn get_canonical_decomp(listed: &FxHashMap<u32, Vec<u32>>, code_point: u32) -> B
     )
 }
 
-pub fn map_fcd() {
+pub fn map_fcd(paths: &Paths) -> Result<()> {
+    // The output of map_decomps is needed here
+    let decomp: DecompTable = read_table(&paths.out("decomp"))?;
+
+    let mut map = collect_fcd(paths, &decomp)?;
+    map.retain(|_, packed| *packed != 0);
+
+    let mut sorted: Vec<(u32, u16)> = map
+        .iter()
+        .map(|(&code_point, &value)| (code_point, value))
+        .collect();
+    sorted.sort_unstable_by_key(|&(code_point, _)| code_point);
+
+    // Write to JSON for debugging
+    write_json(&paths.json("fcd.json"), &sorted)?;
+
+    // Write to bincode; this is what we actually use
+    let table = build_fcd_table(&map);
+    write_table(&paths.out("fcd"), &table)
+}
+
+/// Collects the first and last combining classes of every canonical decomposition, including
+/// those where both are zero
+pub fn collect_fcd(paths: &Paths, decomp: &DecompTable) -> Result<FxHashMap<u32, u16>> {
+    let path = paths.unicode_data();
+    let uni_data = read_input(&path)?;
+
     let mut map: FxHashMap<u32, u16> = FxHashMap::default();
 
-    for line in UNI_DATA.lines() {
+    for (i, line) in uni_data.lines().enumerate() {
         if line.is_empty() {
             continue;
         }
 
-        let left_of_semicolon = line.split(';').next().unwrap();
+        let left_of_semicolon = line.split(';').next().unwrap_or_default();
 
-        let code_point = u32::from_str_radix(left_of_semicolon, 16).unwrap();
+        let code_point = SourceLine::new(&path, i).hex_u32(left_of_semicolon)?;
         if IGNORED_RANGES.iter().any(|r| r.contains(&code_point)) {
             continue;
         }
 
-        let Some(canon_decomp) = DECOMP.get(code_point) else {
+        let Some(canon_decomp) = decomp.get(code_point) else {
             continue;
         };
 
@@ -229,27 +280,10 @@ pub fn map_fcd() {
         let last_cc = get_ccc(canon_decomp[canon_decomp.len() - 1]) as u8;
 
         let packed = (u16::from(first_cc) << 8) | u16::from(last_cc);
-        if packed == 0 {
-            continue;
-        }
-
         map.insert(code_point, packed);
     }
 
-    let mut sorted: Vec<(u32, u16)> = map
-        .iter()
-        .map(|(&code_point, &value)| (code_point, value))
-        .collect();
-    sorted.sort_unstable_by_key(|&(code_point, _)| code_point);
-
-    // Write to JSON for debugging
-    let json_bytes = serde_json::to_vec(&sorted).unwrap();
-    std::fs::write("json/cldr-46_1/fcd.json", json_bytes).unwrap();
-
-    // Write to bincode; this is what we actually use
-    let table = build_fcd_table(&map);
-    let bytes = postcard::to_allocvec(&table).unwrap();
-    std::fs::write("bincode/cldr-46_1/fcd", bytes).unwrap();
+    Ok(map)
 }
 
 #[must_use]
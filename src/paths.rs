@@ -0,0 +1,148 @@
+#![allow(clippy::missing_errors_doc)]
+
+use crate::{
+    error::{Error, Result},
+    version::DataVersion,
+};
+use feruca::Tailoring;
+use std::path::{Path, PathBuf};
+
+/// Locations of the Unicode/CLDR input files and of the generated tables
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Paths {
+    /// Directory holding `allkeys.txt`, `allkeys_CLDR.txt` and `UnicodeData.txt`, and for newer
+    /// releases `FractionalUCA_SHORT.txt`
+    pub data_dir: PathBuf,
+    /// Directory that receives the postcard tables
+    pub out_dir: PathBuf,
+    /// Directory that receives the JSON debugging output
+    pub json_dir: PathBuf,
+    /// Directory that receives tables in the version's legacy layout, if it has one
+    pub legacy_dir: PathBuf,
+    /// The data release in `data_dir`
+    pub version: DataVersion,
+}
+
+impl Default for Paths {
+    fn default() -> Self {
+        Self::for_version(DataVersion::default())
+    }
+}
+
+impl Paths {
+    pub fn new(
+        data_dir: impl Into<PathBuf>,
+        out_dir: impl Into<PathBuf>,
+        json_dir: impl Into<PathBuf>,
+    ) -> Self {
+        let out_dir = out_dir.into();
+
+        Self {
+            data_dir: data_dir.into(),
+            legacy_dir: out_dir.clone(),
+            out_dir,
+            json_dir: json_dir.into(),
+            version: DataVersion::default(),
+        }
+    }
+
+    /// The checked-in directories for a release. Older releases keep their legacy tables at the
+    /// top of `bincode/<version>`, so the current layout goes into a `postcard` subdirectory.
+    #[must_use]
+    pub fn for_version(version: DataVersion) -> Self {
+        let name = version.dir_name();
+        let legacy_dir = PathBuf::from("bincode").join(name);
+        let out_dir = if version.legacy_layout().is_some() {
+            legacy_dir.join("postcard")
+        } else {
+            legacy_dir.clone()
+        };
+
+        Self {
+            data_dir: PathBuf::from("unicode-data").join(name),
+            out_dir,
+            json_dir: PathBuf::from("json").join(name),
+            legacy_dir,
+            version,
+        }
+    }
+
+    #[must_use]
+    pub fn allkeys(&self, keys: Tailoring) -> PathBuf {
+        if keys == Tailoring::Ducet {
+            self.data_dir.join("allkeys.txt")
+        } else {
+            self.data_dir.join("allkeys_CLDR.txt")
+        }
+    }
+
+    #[must_use]
+    pub fn fractional_uca(&self) -> PathBuf {
+        self.data_dir.join("FractionalUCA_SHORT.txt")
+    }
+
+    /// Collation rules in LDML syntax, e.g. `collation/de-phonebook.txt`
+    #[must_use]
+    pub fn collation_rules(&self, name: &str) -> PathBuf {
+        self.data_dir.join("collation").join(format!("{name}.txt"))
+    }
+
+    /// A UCA or CLDR conformance test file, e.g. `CollationTest_CLDR_SHIFTED.txt`
+    #[must_use]
+    pub fn collation_test(&self, name: &str) -> PathBuf {
+        self.data_dir.join(format!("CollationTest_{name}.txt"))
+    }
+
+    /// The primary-remapping tailorings to build for this release, e.g. `arabic_script`
+    #[must_use]
+    pub fn tailorings(&self) -> PathBuf {
+        self.data_dir.join("tailorings.json")
+    }
+
+    #[must_use]
+    pub fn unicode_data(&self) -> PathBuf {
+        self.data_dir.join("UnicodeData.txt")
+    }
+
+    #[must_use]
+    pub fn out(&self, name: &str) -> PathBuf {
+        self.out_dir.join(name)
+    }
+
+    #[must_use]
+    pub fn json(&self, name: &str) -> PathBuf {
+        self.json_dir.join(name)
+    }
+
+    #[must_use]
+    pub fn legacy(&self, name: &str) -> PathBuf {
+        self.legacy_dir.join(name)
+    }
+}
+
+/// Writes an output file, creating its parent directories as needed
+pub fn write_output(path: &Path, bytes: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+    }
+    std::fs::write(path, bytes).map_err(|e| Error::io(path, e))
+}
+
+pub fn read_input(path: &Path) -> Result<String> {
+    std::fs::read_to_string(path).map_err(|e| Error::io(path, e))
+}
+
+pub fn read_table<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T> {
+    let bytes = std::fs::read(path).map_err(|e| Error::io(path, e))?;
+    postcard::from_bytes(&bytes).map_err(|e| Error::serialization(path, e))
+}
+
+pub fn write_table(path: &Path, table: &impl serde::Serialize) -> Result<()> {
+    let bytes = postcard::to_allocvec(table).map_err(|e| Error::serialization(path, e))?;
+    write_output(path, &bytes)
+}
+
+pub fn write_json(path: &Path, value: &impl serde::Serialize) -> Result<()> {
+    let bytes = serde_json::to_vec(value).map_err(|e| Error::serialization(path, e))?;
+    write_output(path, &bytes)
+}
@@ -0,0 +1,329 @@
+#![allow(clippy::missing_errors_doc)]
+
+use crate::{
+    allkeys::{CollationElement, CollationElementTable},
+    collation::{BUMP, MultiMappings, PrefixMappings, SHIFT, low_from_mappings},
+    delta::write_tailoring,
+    error::{Error, Result},
+    legacy::{write_legacy_multis, write_legacy_singles},
+    paths::{Paths, read_input, write_json},
+    tailor::TailoredMappings,
+};
+use rustc_hash::FxHashMap;
+use serde::Deserialize;
+use std::{collections::HashMap, hash::BuildHasher, ops::RangeInclusive};
+
+// The singles and contractions that a tailoring moves
+type Moved = (FxHashMap<u32, Box<[u32]>>, MultiMappings);
+
+/// A tailoring that moves CLDR root primaries, built up from range shifts, explicit maps and
+/// closures, and written to `tailoring/<name>`
+///
+/// Each primary goes through the remappings in the order they were added, and the first one that
+/// takes it decides its new value; primaries that none of them take are left alone.
+pub struct Tailoring {
+    name: String,
+    legacy: bool,
+    remaps: Vec<Remap>,
+}
+
+enum Remap {
+    Shift {
+        primaries: RangeInclusive<u16>,
+        by: i32,
+    },
+    Map(FxHashMap<u16, u16>),
+    With(Box<dyn Fn(u16) -> Option<u16>>),
+}
+
+impl Tailoring {
+    #[must_use]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            legacy: false,
+            remaps: Vec::new(),
+        }
+    }
+
+    /// Moves a range of primaries by a fixed offset. Any that would fall outside `u16` are left
+    /// alone.
+    #[must_use]
+    pub fn shift(mut self, primaries: RangeInclusive<u16>, by: i32) -> Self {
+        self.remaps.push(Remap::Shift { primaries, by });
+        self
+    }
+
+    /// Moves each primary in a list of pairs `(from, to)`
+    #[must_use]
+    pub fn map(mut self, primaries: impl IntoIterator<Item = (u16, u16)>) -> Self {
+        self.remaps
+            .push(Remap::Map(primaries.into_iter().collect()));
+        self
+    }
+
+    /// Moves primaries by a function, which returns `None` for those it leaves to later remappings
+    #[must_use]
+    pub fn with(mut self, map_primary: impl Fn(u16) -> Option<u16> + 'static) -> Self {
+        self.remaps.push(Remap::With(Box::new(map_primary)));
+        self
+    }
+
+    /// Also writes the tailored mappings, on their own, in the version's legacy layout
+    #[must_use]
+    pub const fn legacy(mut self) -> Self {
+        self.legacy = true;
+        self
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The new value of a primary, or `None` if the tailoring doesn't move it
+    #[must_use]
+    pub fn map_primary(&self, primary: u16) -> Option<u16> {
+        self.remaps.iter().find_map(|remap| match remap {
+            Remap::Shift { primaries, by } => primaries
+                .contains(&primary)
+                .then(|| u16::try_from(i32::from(primary) + by).ok())
+                .flatten(),
+            Remap::Map(map) => map.get(&primary).copied(),
+            Remap::With(map_primary) => map_primary(primary),
+        })
+    }
+
+    /// The CLDR root mappings with at least one primary moved, singles and contractions
+    pub fn collect(&self, paths: &Paths) -> Result<Moved> {
+        let table = CollationElementTable::read(&paths.allkeys(feruca::Tailoring::default()))?;
+
+        let mut singles = FxHashMap::default();
+        let mut multis = MultiMappings::default();
+
+        for entry in &table.entries {
+            let mut moved = false;
+            let weights: Box<[u32]> = entry
+                .elements
+                .iter()
+                .map(|&element| {
+                    let primary = self.map_primary(element.primary);
+                    moved |= primary.is_some();
+
+                    CollationElement {
+                        primary: primary.unwrap_or(element.primary),
+                        ..element
+                    }
+                    .pack()
+                })
+                .collect();
+
+            if !moved {
+                continue;
+            }
+            if let [code_point] = *entry.code_points {
+                singles.insert(code_point, weights);
+            } else {
+                multis.insert(entry.code_points.clone(), weights);
+            }
+        }
+
+        Ok((singles, multis))
+    }
+
+    /// Writes the CLDR trie with this tailoring applied, along with its delta, and returns the
+    /// mappings that went into it. A low table is written too if any low code point moved.
+    pub fn map_trie<S1: BuildHasher, S2: BuildHasher>(
+        &self,
+        paths: &Paths,
+        cldr_singles: &HashMap<u32, Box<[u32]>, S1>,
+        cldr_multis: &HashMap<Box<[u32]>, Box<[u32]>, S2>,
+    ) -> Result<TailoredMappings> {
+        let (singles, multis) = self.collect(paths)?;
+
+        let mut mappings = TailoredMappings {
+            singles: (cldr_singles.iter())
+                .map(|(&k, v)| (k, v.clone()))
+                .chain(singles)
+                .collect(),
+            multis: (cldr_multis.iter())
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .chain(multis)
+                .collect(),
+            prefixes: PrefixMappings::default(),
+        };
+        write_tailoring(paths, &self.name, cldr_singles, cldr_multis, &mut mappings)?;
+
+        let low = low_from_mappings(&mappings.singles, &mappings.multis);
+        if low != low_from_mappings(cldr_singles, cldr_multis) {
+            write_json(
+                &paths.json(&format!("low_{}.json", self.name)),
+                &low.as_slice(),
+            )?;
+        }
+
+        Ok(mappings)
+    }
+
+    /// Writes `tailoring/<name>_sing` and `tailoring/<name>_multi` in the legacy layout, if the
+    /// tailoring asks for them and the version has one
+    pub fn map_legacy(&self, paths: &Paths) -> Result<()> {
+        let Some(layout) = paths.version.legacy_layout().filter(|_| self.legacy) else {
+            return Ok(());
+        };
+        let (singles, multis) = self.collect(paths)?;
+
+        write_legacy_singles(
+            &paths.legacy(&format!("tailoring/{}_sing", self.name)),
+            &singles,
+            layout,
+        )?;
+        write_legacy_multis(
+            &paths.legacy(&format!("tailoring/{}_multi", self.name)),
+            &multis,
+            layout,
+        )
+    }
+
+    /// Reads the tailorings declared in `tailorings.json` in the data directory, in order; none if
+    /// there's no such file
+    ///
+    /// Each is an object with a `name`, an optional `legacy` flag, and lists of `shift` ranges
+    /// (`first`, `last`, `by`) and `map` pairs (`from`, `to`). Primaries are hex, optionally summed
+    /// with `SHIFT` and `BUMP`, e.g. `"239B + BUMP + SHIFT"`, and `by` may be negative. Explicit
+    /// pairs take precedence over ranges. Any other field, such as a `note`, is ignored.
+    pub fn read_config(paths: &Paths) -> Result<Vec<Self>> {
+        let path = paths.tailorings();
+        if !path.is_file() {
+            return Ok(Vec::new());
+        }
+
+        let specs: Vec<TailoringSpec> = serde_json::from_str(&read_input(&path)?)
+            .map_err(|e| Error::invalid(&path, e.to_string()))?;
+
+        let mut tailorings = Vec::with_capacity(specs.len());
+        for spec in specs {
+            let invalid =
+                |message: String| Error::invalid(&path, format!("{}: {message}", spec.name));
+
+            let mut map = Vec::with_capacity(spec.map.len());
+            for MapSpec { from, to } in &spec.map {
+                let pair = parse_primary(from).zip(parse_primary(to));
+                map.push(pair.ok_or_else(|| invalid(format!("invalid pair {from} -> {to}")))?);
+            }
+
+            let mut tailoring = Self::new(&spec.name).map(map);
+            for ShiftSpec { first, last, by } in &spec.shift {
+                let range = parse_primary(first).zip(parse_primary(last));
+                let (Some((first, last)), Some(by)) = (range, parse_offset(by)) else {
+                    return Err(invalid(format!("invalid shift {first}..{last} by {by}")));
+                };
+                let fits = |primary: u16| u16::try_from(i32::from(primary) + by).is_ok();
+                if first > last || !fits(first) || !fits(last) {
+                    return Err(invalid(format!(
+                        "can't shift {first:04X}..{last:04X} by {by}"
+                    )));
+                }
+
+                tailoring = tailoring.shift(first..=last, by);
+            }
+
+            tailorings.push(if spec.legacy {
+                tailoring.legacy()
+            } else {
+                tailoring
+            });
+        }
+
+        Ok(tailorings)
+    }
+}
+
+#[derive(Deserialize)]
+struct TailoringSpec {
+    name: String,
+    #[serde(default)]
+    legacy: bool,
+    #[serde(default)]
+    shift: Vec<ShiftSpec>,
+    #[serde(default)]
+    map: Vec<MapSpec>,
+}
+
+#[derive(Deserialize)]
+struct ShiftSpec {
+    first: String,
+    last: String,
+    by: String,
+}
+
+#[derive(Deserialize)]
+struct MapSpec {
+    from: String,
+    to: String,
+}
+
+// Hex terms, SHIFT and BUMP, joined by `+`
+fn parse_primary(expr: &str) -> Option<u16> {
+    expr.split('+').try_fold(0u16, |sum, term| {
+        let value = match term.trim() {
+            "SHIFT" => SHIFT,
+            "BUMP" => BUMP,
+            hex => u16::from_str_radix(hex, 16).ok()?,
+        };
+        sum.checked_add(value)
+    })
+}
+
+fn parse_offset(expr: &str) -> Option<i32> {
+    expr.trim().strip_prefix('-').map_or_else(
+        || parse_primary(expr).map(i32::from),
+        |magnitude| parse_primary(magnitude).map(|m| -i32::from(m)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remaps_in_order() {
+        let tailoring = Tailoring::new("test")
+            .map([(0x2000, 0x1000)])
+            .shift(0x2000..=0x20FF, -0x100)
+            .with(|primary| (primary == 0x3000).then_some(0x3001));
+
+        assert_eq!(tailoring.map_primary(0x2000), Some(0x1000));
+        assert_eq!(tailoring.map_primary(0x20FF), Some(0x1FFF));
+        assert_eq!(tailoring.map_primary(0x3000), Some(0x3001));
+        assert_eq!(tailoring.map_primary(0x2100), None);
+        assert_eq!(Tailoring::new("test").shift(0..=1, -1).map_primary(0), None);
+
+        assert_eq!(
+            parse_primary("239B + BUMP + SHIFT"),
+            Some(0x239B + BUMP + SHIFT)
+        );
+        assert_eq!(parse_offset("-7DA"), Some(-0x7DA));
+        assert_eq!(parse_primary("FFFF + BUMP"), None);
+    }
+
+    // Arabic has to land in the gap that SHIFT opened below Latin
+    #[test]
+    fn arabic_script_fits() {
+        const LAST_PRIMARY_BEFORE_LATIN: u16 = 0x237F;
+        const FIRST_LATIN_PRIMARY: u16 = 0x2380 + SHIFT; // 0061, "LATIN SMALL LETTER A"
+        const FIRST_ARABIC_PRIMARY: u16 = 0x2A68; // 0621, "ARABIC LETTER HAMZA"
+        const LAST_ARABIC_PRIMARY: u16 = 0x2B56; // 088E, "ARABIC VERTICAL TAIL"
+
+        let tailorings = Tailoring::read_config(&Paths::default()).unwrap();
+        let arabic_script = tailorings
+            .iter()
+            .find(|t| t.name() == "arabic_script")
+            .unwrap();
+
+        let first = arabic_script.map_primary(FIRST_ARABIC_PRIMARY).unwrap();
+        let last = arabic_script.map_primary(LAST_ARABIC_PRIMARY).unwrap();
+        assert!(first > LAST_PRIMARY_BEFORE_LATIN);
+        assert!(last < FIRST_LATIN_PRIMARY);
+    }
+}
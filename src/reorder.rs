@@ -0,0 +1,360 @@
+#![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
+
+use crate::{
+    collation::{PrefixMappings, collect_singles, low_from_mappings, pack_weights, unpack_weights},
+    delta::write_tailoring,
+    error::{Error, Result},
+    fractional::{FractionalElement, FractionalTable, IMPLICIT_FLOOR},
+    paths::{Paths, write_json},
+    tailor::TailoredMappings,
+};
+use feruca::Tailoring;
+use rustc_hash::FxHashMap;
+use std::{collections::HashMap, hash::BuildHasher, ops::RangeInclusive};
+
+// FractionalUCA marks the first primary of each script and special group with a contraction of
+// U+FDD1 and a sample character, e.g. `FDD1 0628; [67 04 02, 05, 05] # ARABIC first primary`
+const SCRIPT_START_MARKER: u32 = 0xFDD1;
+
+// The tokens naming a script or group, and its first fractional primary
+type ScriptStart = (Vec<String>, Box<[u8]>);
+
+// The special groups, in root order, as they're named in `[reorder ...]` and in FractionalUCA
+const SPECIAL_GROUPS: [(&str, &str); 5] = [
+    ("space", "SPACE"),
+    ("punct", "PUNCTUATION"),
+    ("symbol", "SYMBOL"),
+    ("currency", "CURRENCY"),
+    ("digit", "DIGIT"),
+];
+
+/// A script or special group that can be moved as a block, with the adjusted `allkeys_CLDR.txt`
+/// primaries that it covers
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReorderGroup {
+    /// Script codes and group names that select this group, e.g. `["Hira", "Hrkt", "Kana"]`
+    pub tokens: Vec<String>,
+    /// Includes any gap up to the next group
+    pub primaries: RangeInclusive<u16>,
+}
+
+impl ReorderGroup {
+    fn matches(&self, code: &str) -> bool {
+        let token = SPECIAL_GROUPS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(code))
+            .map_or(code, |(_, token)| token);
+
+        self.tokens.iter().any(|t| t.eq_ignore_ascii_case(token))
+    }
+}
+
+/// Collects the reorder groups in root order. Scripts whose primaries are all implicit (Han,
+/// Tangut, Nushu, Khitan) have no primaries to move and are left out.
+pub fn collect_reorder_groups(paths: &Paths) -> Result<Vec<ReorderGroup>> {
+    let table = FractionalTable::read(&paths.fractional_uca())?;
+    let starts = script_starts(&table)?;
+    let singles = collect_singles(paths, Tailoring::default())?;
+
+    // The lowest and highest allkeys primaries of the mappings that fall under each script start
+    let mut bounds: Vec<Option<(u16, u16)>> = vec![None; starts.len()];
+
+    for entry in &table.entries {
+        let ([code_point], true) = (&*entry.code_points, entry.prefix.is_empty()) else {
+            continue;
+        };
+        let Some(FractionalElement::Explicit(weights)) = entry.elements.first() else {
+            continue;
+        };
+        let Some(allkeys) = singles.get(code_point) else {
+            continue;
+        };
+
+        let primary = unpack_weights(allkeys[0]).1;
+        let i = starts.partition_point(|(_, start)| *start <= weights.primary);
+        if primary == 0 || i == 0 {
+            continue;
+        }
+
+        let (low, high) = bounds[i - 1].get_or_insert((primary, primary));
+        *low = (*low).min(primary);
+        *high = (*high).max(primary);
+    }
+
+    let found: Vec<(Vec<String>, (u16, u16))> = starts
+        .into_iter()
+        .zip(bounds)
+        .filter_map(|((tokens, _), bounds)| Some((tokens, bounds?)))
+        .collect();
+
+    let mut groups = Vec::with_capacity(found.len());
+
+    for (i, (tokens, (low, high))) in found.iter().enumerate() {
+        if *low >= IMPLICIT_FLOOR {
+            break;
+        }
+
+        // Each group runs up to the next one, so that gaps move along with the script below them.
+        // The gap below the implicit primaries belongs to no group.
+        let end = match found.get(i + 1) {
+            Some((_, (next_low, _))) if *next_low >= IMPLICIT_FLOOR => *high,
+            Some((next_tokens, (next_low, _))) => {
+                if next_low <= high {
+                    return Err(Error::invalid(
+                        &table.path,
+                        format!(
+                            "{} and {} overlap in allkeys_CLDR.txt",
+                            tokens.join("/"),
+                            next_tokens.join("/")
+                        ),
+                    ));
+                }
+                next_low - 1
+            }
+            None => *high,
+        };
+
+        groups.push(ReorderGroup {
+            tokens: tokens.clone(),
+            primaries: *low..=end,
+        });
+    }
+
+    Ok(groups)
+}
+
+// The first fractional primary of each script and special group, sorted, with the tokens that
+// name it. The `[top_byte]` lines list the scripts under each lead byte in the same order as the
+// start markers, which is how the two are matched up.
+fn script_starts(table: &FractionalTable) -> Result<Vec<ScriptStart>> {
+    let markers: Vec<&[u8]> = table
+        .entries
+        .iter()
+        .filter(|entry| entry.code_points[0] == SCRIPT_START_MARKER && entry.prefix.is_empty())
+        .filter_map(|entry| match entry.elements.first() {
+            Some(FractionalElement::Explicit(weights)) if !weights.primary.is_empty() => {
+                Some(&*weights.primary)
+            }
+            _ => None,
+        })
+        .collect();
+
+    let mut starts: Vec<ScriptStart> = Vec::new();
+
+    for run in table.top_bytes.chunk_by(|a, b| a.tokens == b.tokens) {
+        let leads = run[0].lead..=run[run.len() - 1].lead;
+        let primaries: Vec<&[u8]> = markers
+            .iter()
+            .filter(|primary| leads.contains(&primary[0]))
+            .copied()
+            .collect();
+
+        // Drops markers such as COMPRESS, which aren't reordering tokens
+        let tokens: Vec<&String> = run[0]
+            .tokens
+            .iter()
+            .filter(|t| table.reordering_tokens.iter().any(|r| r.token == **t))
+            .collect();
+
+        if primaries.is_empty() {
+            // Terminators and separators, and the reserved bytes around Latin
+            continue;
+        }
+
+        if primaries.len() == tokens.len() {
+            for (token, primary) in tokens.into_iter().zip(primaries) {
+                starts.push((vec![token.clone()], primary.into()));
+            }
+        } else if primaries.iter().all(|p| *p == primaries[0]) {
+            // Aliases such as Hira, Hrkt and Kana, which share one start
+            starts.push((tokens.into_iter().cloned().collect(), primaries[0].into()));
+        } else {
+            return Err(Error::invalid(
+                &table.path,
+                format!(
+                    "can't match {} script starts to the tokens of lead byte {:02X}",
+                    primaries.len(),
+                    run[0].lead
+                ),
+            ));
+        }
+    }
+
+    starts.sort_by(|a, b| a.1.cmp(&b.1));
+
+    // Meroitic Cursive and Meroitic Hieroglyphs, for one, share a start
+    let mut merged: Vec<ScriptStart> = Vec::with_capacity(starts.len());
+    for (tokens, primary) in starts {
+        match merged.last_mut() {
+            Some((prev_tokens, prev_primary)) if *prev_primary == primary => {
+                prev_tokens.extend(tokens);
+            }
+            _ => merged.push((tokens, primary)),
+        }
+    }
+
+    Ok(merged)
+}
+
+/// A permutation of reorder groups, applied to primary weights
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrimaryReordering {
+    /// Old ranges of the groups that move, with the offset to add to each primary
+    moves: Vec<(RangeInclusive<u16>, i32)>,
+}
+
+impl PrimaryReordering {
+    /// Follows `[reorder ...]` in LDML: special groups that aren't listed keep their place at the
+    /// start, then come the listed groups in order, then everything else in root order.
+    /// Codes are script codes (`Latn`, `Cyrl`) or group names (`digit`, `punct`), in any case.
+    pub fn new(groups: &[ReorderGroup], codes: &[String]) -> Result<Self, String> {
+        let mut listed: Vec<usize> = Vec::with_capacity(codes.len());
+        for code in codes {
+            let i = groups
+                .iter()
+                .position(|group| group.matches(code))
+                .ok_or_else(|| format!("unknown or implicit reorder code: {code}"))?;
+            if listed.contains(&i) {
+                return Err(format!("reorder code listed twice: {code}"));
+            }
+            listed.push(i);
+        }
+
+        let is_special = |i: &usize| {
+            SPECIAL_GROUPS
+                .iter()
+                .any(|(_, token)| groups[*i].tokens.iter().any(|t| t == token))
+        };
+
+        let mut order: Vec<usize> = (0..groups.len())
+            .filter(|i| is_special(i) && !listed.contains(i))
+            .collect();
+        order.extend(&listed);
+        let rest: Vec<usize> = (0..groups.len()).filter(|i| !order.contains(i)).collect();
+        order.extend(rest);
+
+        let mut moves = Vec::new();
+        let mut next = groups.first().map_or(0, |group| *group.primaries.start());
+
+        for i in order {
+            let primaries = groups[i].primaries.clone();
+            let offset = i32::from(next) - i32::from(*primaries.start());
+            next += primaries.end() - primaries.start() + 1;
+            if offset != 0 {
+                moves.push((primaries, offset));
+            }
+        }
+
+        Ok(Self { moves })
+    }
+
+    #[must_use]
+    pub fn map_primary(&self, primary: u16) -> u16 {
+        self.moves
+            .iter()
+            .find(|(range, _)| range.contains(&primary))
+            .map_or(primary, |(_, offset)| {
+                u16::try_from(i32::from(primary) + offset).unwrap()
+            })
+    }
+
+    #[must_use]
+    pub fn map_weights(&self, weights: &[u32]) -> Box<[u32]> {
+        weights
+            .iter()
+            .map(|&packed| {
+                let (variable, primary, secondary, tertiary) = unpack_weights(packed);
+                pack_weights(variable, self.map_primary(primary), secondary, tertiary)
+            })
+            .collect()
+    }
+}
+
+/// Writes a CLDR trie with the given `[reorder ...]` codes applied, along with its delta and low
+/// table
+pub fn map_reorder_trie<S1: BuildHasher, S2: BuildHasher>(
+    paths: &Paths,
+    codes: &[String],
+    cldr_singles: &HashMap<u32, Box<[u32]>, S1>,
+    cldr_multis: &HashMap<Box<[u32]>, Box<[u32]>, S2>,
+) -> Result<()> {
+    let groups = collect_reorder_groups(paths)?;
+    let reordering = PrimaryReordering::new(&groups, codes)
+        .map_err(|message| Error::invalid(&paths.fractional_uca(), message))?;
+
+    let singles: FxHashMap<u32, Box<[u32]>> = cldr_singles
+        .iter()
+        .map(|(&code_point, weights)| (code_point, reordering.map_weights(weights)))
+        .collect();
+    let multis: FxHashMap<Box<[u32]>, Box<[u32]>> = cldr_multis
+        .iter()
+        .map(|(code_points, weights)| (code_points.clone(), reordering.map_weights(weights)))
+        .collect();
+
+    let name = format!("reorder_{}", codes.join("_").to_ascii_lowercase());
+
+    let mut mappings = TailoredMappings {
+        singles,
+        multis,
+        prefixes: PrefixMappings::default(),
+    };
+    write_tailoring(paths, &name, cldr_singles, cldr_multis, &mut mappings)?;
+
+    // Moving Latin changes the low code points too, which feruca doesn't look up in the trie
+    write_json(
+        &paths.json(&format!("low_{name}.json")),
+        &low_from_mappings(&mappings.singles, &mappings.multis).as_slice(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(tokens: &[&str], primaries: RangeInclusive<u16>) -> ReorderGroup {
+        ReorderGroup {
+            tokens: tokens.iter().map(ToString::to_string).collect(),
+            primaries,
+        }
+    }
+
+    fn codes(codes: &[&str]) -> Vec<String> {
+        codes.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn reorder_groups() {
+        let groups = [
+            group(&["SPACE"], 0x100..=0x1FF),
+            group(&["DIGIT"], 0x200..=0x2FF),
+            group(&["Latn"], 0x300..=0x4FF),
+            group(&["Grek"], 0x500..=0x53F),
+            group(&["Cyrl"], 0x540..=0x5FF),
+            group(&["Hira", "Hrkt", "Kana"], 0x600..=0x6FF),
+        ];
+
+        // Cyrillic first: the space and digit groups stay put, since they aren't listed
+        let cyrillic = PrimaryReordering::new(&groups, &codes(&["cyrl"])).unwrap();
+        assert_eq!(cyrillic.map_primary(0x150), 0x150);
+        assert_eq!(cyrillic.map_primary(0x540), 0x300);
+        assert_eq!(cyrillic.map_primary(0x300), 0x3C0);
+        assert_eq!(cyrillic.map_primary(0x500), 0x5C0);
+        assert_eq!(cyrillic.map_primary(0x600), 0x600);
+        assert_eq!(cyrillic.map_primary(0), 0);
+
+        // Greek before Latin, and digits after both
+        let greek = PrimaryReordering::new(&groups, &codes(&["Grek", "Latn", "digit"])).unwrap();
+        assert_eq!(greek.map_primary(0x500), 0x200);
+        assert_eq!(greek.map_primary(0x300), 0x240);
+        assert_eq!(greek.map_primary(0x200), 0x440);
+        assert_eq!(greek.map_primary(0x540), 0x540);
+
+        assert_eq!(
+            greek.map_weights(&[pack_weights(false, 0x501, 0x20, 2)])[0],
+            pack_weights(false, 0x201, 0x20, 2)
+        );
+
+        assert!(PrimaryReordering::new(&groups, &codes(&["Kana", "Hira"])).is_err());
+        assert!(PrimaryReordering::new(&groups, &codes(&["Hani"])).is_err());
+    }
+}
@@ -0,0 +1,542 @@
+#![allow(clippy::missing_errors_doc)]
+
+use crate::{
+    error::{Error, Result, SourceLine},
+    paths::{Paths, read_input},
+};
+use std::path::{Path, PathBuf};
+
+// Imports can nest, but not forever
+const MAX_IMPORT_DEPTH: usize = 8;
+
+// BCP 47 collation types that LDML spells differently, as in `[import de-u-co-phonebk]`
+const COLLATION_TYPE_ALIASES: [(&str, &str); 4] = [
+    ("dict", "dictionary"),
+    ("gb2312", "gb2312han"),
+    ("phonebk", "phonebook"),
+    ("trad", "traditional"),
+];
+
+/// The strength of a relation: `<`, `<<`, `<<<` or `=`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Primary,
+    Secondary,
+    Tertiary,
+    Identical,
+}
+
+/// A set of CLDR collation rules, in the syntax of LDML part 5, section 3
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CollationRules {
+    pub path: PathBuf,
+    /// Locale IDs from `[import ...]`, e.g. `de-u-co-phonebk`
+    pub imports: Vec<String>,
+    /// Script codes and group names from `[reorder ...]`
+    pub reorder: Vec<String>,
+    /// Every other `[name value]` setting, e.g. `("caseFirst", "upper")`; these are recorded but
+    /// not applied
+    pub settings: Vec<(String, String)>,
+    pub chains: Vec<RuleChain>,
+}
+
+/// A reset and the relations that follow it, e.g. `&[before 1]ǀ < å <<< Å`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuleChain {
+    /// From `&[before N]`
+    pub before: Option<Level>,
+    pub reset: Box<[u32]>,
+    pub relations: Vec<Relation>,
+    /// One-based line number of the reset
+    pub line: usize,
+}
+
+/// One tailored string, e.g. `<<< þ/h`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Relation {
+    pub level: Level,
+    /// Code points before `|`, which must come just before the others for the relation to apply
+    pub prefix: Box<[u32]>,
+    /// Two or more code points make a contraction
+    pub code_points: Box<[u32]>,
+    /// Code points after `/`, whose collation elements are appended
+    pub extension: Box<[u32]>,
+}
+
+impl CollationRules {
+    /// Reads the rules for a tailoring, along with everything they import, from the `collation`
+    /// directory under the data directory
+    pub fn read(paths: &Paths, name: &str) -> Result<Self> {
+        Self::read_nested(paths, name, 0)
+    }
+
+    fn read_nested(paths: &Paths, name: &str, depth: usize) -> Result<Self> {
+        let path = paths.collation_rules(name);
+        let data = read_input(&path)?;
+        let mut rules = Self::parse(&path, &data)?;
+
+        if rules.imports.is_empty() {
+            return Ok(rules);
+        }
+        if depth == MAX_IMPORT_DEPTH {
+            return Err(Error::invalid(&path, "imports nest too deeply"));
+        }
+
+        // Imported rules come before the importing ones
+        let mut merged = Self {
+            path: rules.path.clone(),
+            imports: rules.imports.clone(),
+            ..Self::default()
+        };
+        for import in &rules.imports {
+            let imported = Self::read_nested(paths, &rules_name(import), depth + 1)?;
+            merged.reorder.extend(imported.reorder);
+            merged.settings.extend(imported.settings);
+            merged.chains.extend(imported.chains);
+        }
+        if !rules.reorder.is_empty() {
+            merged.reorder = std::mem::take(&mut rules.reorder);
+        }
+        merged.settings.append(&mut rules.settings);
+        merged.chains.append(&mut rules.chains);
+
+        Ok(merged)
+    }
+
+    /// Parses rule text; `path` is used only for error messages
+    pub fn parse(path: &Path, data: &str) -> Result<Self> {
+        let mut parser = Parser {
+            path,
+            chars: data.chars().collect(),
+            pos: 0,
+            line: 0,
+        };
+        let mut rules = Self {
+            path: path.to_path_buf(),
+            ..Self::default()
+        };
+
+        loop {
+            parser.skip_space();
+            match parser.peek() {
+                None => break,
+                Some('&') => {
+                    parser.pos += 1;
+                    rules.chains.push(parser.chain()?);
+                }
+                Some('[') => {
+                    let (name, value) = parser.setting()?;
+                    match name.as_str() {
+                        "import" => rules
+                            .imports
+                            .extend(value.split_whitespace().map(Into::into)),
+                        "reorder" => {
+                            rules.reorder = value.split_whitespace().map(Into::into).collect();
+                        }
+                        _ => rules.settings.push((name, value)),
+                    }
+                }
+                Some(c) => return Err(parser.error(format!("expected a reset, found '{c}'"))),
+            }
+        }
+
+        Ok(rules)
+    }
+}
+
+/// The file name, minus `.txt`, for an imported locale ID: `de-u-co-phonebk` is `de-phonebook`,
+/// and `sv-u-co-standard` is plain `sv`
+#[must_use]
+pub fn rules_name(locale: &str) -> String {
+    let Some((language, collation)) = locale.split_once("-u-co-") else {
+        return locale.to_string();
+    };
+
+    let collation = COLLATION_TYPE_ALIASES
+        .iter()
+        .find(|(bcp47, _)| *bcp47 == collation)
+        .map_or(collation, |(_, ldml)| ldml);
+
+    if collation == "standard" {
+        language.to_string()
+    } else {
+        format!("{language}-{collation}")
+    }
+}
+
+struct Parser<'a> {
+    path: &'a Path,
+    chars: Vec<char>,
+    pos: usize,
+    // Zero-based, like the line indices given to SourceLine::new
+    line: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: impl Into<String>) -> Error {
+        SourceLine::new(self.path, self.line).error(message)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    // Whitespace and comments mean nothing outside of quotes
+    fn skip_space(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '#' {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.pos += 1;
+                }
+            } else if c.is_whitespace() {
+                self.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn eat(&mut self, s: &str) -> bool {
+        let matches = s
+            .chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c));
+        if matches {
+            self.pos += s.chars().count();
+        }
+        matches
+    }
+
+    // E.g. `[caseFirst upper]` or `[suppressContractions [Ћ]]`, keeping any nested brackets
+    fn setting(&mut self) -> Result<(String, String)> {
+        let start_line = self.line;
+        self.next();
+
+        let mut text = String::new();
+        let mut depth = 1;
+        loop {
+            let Some(c) = self.next() else {
+                self.line = start_line;
+                return Err(self.error("unterminated setting"));
+            };
+            match c {
+                '[' => depth += 1,
+                ']' if depth == 1 => break,
+                ']' => depth -= 1,
+                _ => {}
+            }
+            text.push(c);
+        }
+
+        let text = text.trim();
+        let (name, value) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        Ok((name.to_string(), value.trim().to_string()))
+    }
+
+    fn chain(&mut self) -> Result<RuleChain> {
+        self.skip_space();
+        let line = self.line + 1;
+
+        let before = if self.eat("[before") {
+            self.skip_space();
+            let level = match self.next() {
+                Some('1') => Level::Primary,
+                Some('2') => Level::Secondary,
+                Some('3') => Level::Tertiary,
+                _ => return Err(self.error("[before] takes 1, 2 or 3")),
+            };
+            self.skip_space();
+            if self.next() != Some(']') {
+                return Err(self.error("unterminated [before]"));
+            }
+            Some(level)
+        } else {
+            None
+        };
+
+        self.skip_space();
+        if self.peek() == Some('[') {
+            let (name, value) = self.setting()?;
+            return Err(self.error(format!(
+                "special reset positions are not supported: [{name} {value}]"
+            )));
+        }
+
+        let reset = self.text()?;
+        if reset.is_empty() {
+            return Err(self.error("empty reset"));
+        }
+
+        let mut relations = Vec::new();
+        loop {
+            self.skip_space();
+            let level = if self.eat("<<<<") {
+                return Err(self.error("quaternary relations are not supported"));
+            } else if self.eat("<<<") {
+                Level::Tertiary
+            } else if self.eat("<<") {
+                Level::Secondary
+            } else if self.eat("<") {
+                Level::Primary
+            } else if self.eat("=") {
+                Level::Identical
+            } else {
+                break;
+            };
+
+            // A starred relation applies to each code point in turn, and allows ranges: `<*a-d`
+            if self.eat("*") {
+                for code_point in self.star_list()? {
+                    relations.push(Relation {
+                        level,
+                        prefix: Box::default(),
+                        code_points: Box::from([code_point]),
+                        extension: Box::default(),
+                    });
+                }
+                continue;
+            }
+
+            let mut code_points = self.text()?;
+            if code_points.is_empty() {
+                return Err(self.error("empty relation"));
+            }
+
+            self.skip_space();
+            let prefix = if self.eat("|") {
+                let prefix = std::mem::replace(&mut code_points, self.text()?);
+                if code_points.is_empty() {
+                    return Err(self.error("empty relation after context"));
+                }
+                self.skip_space();
+                prefix
+            } else {
+                Box::default()
+            };
+            let extension = if self.eat("/") {
+                let extension = self.text()?;
+                if extension.is_empty() {
+                    return Err(self.error("empty extension"));
+                }
+                extension
+            } else {
+                Box::default()
+            };
+
+            relations.push(Relation {
+                level,
+                prefix,
+                code_points,
+                extension,
+            });
+        }
+
+        if relations.is_empty() {
+            return Err(self.error("reset without relations"));
+        }
+
+        Ok(RuleChain {
+            before,
+            reset,
+            relations,
+            line,
+        })
+    }
+
+    fn star_list(&mut self) -> Result<Vec<u32>> {
+        let text = self.text_with(true)?;
+        let mut code_points = Vec::with_capacity(text.len());
+        let mut i = 0;
+
+        while i < text.len() {
+            match text[i] {
+                RANGE_MARK if i + 1 < text.len() && !code_points.is_empty() => {
+                    let first: u32 = *code_points.last().unwrap();
+                    let last = text[i + 1];
+                    if last < first {
+                        return Err(self.error("range out of order in starred relation"));
+                    }
+                    code_points.extend(first + 1..=last);
+                    i += 2;
+                }
+                RANGE_MARK => return Err(self.error("dangling '-' in starred relation")),
+                code_point => {
+                    code_points.push(code_point);
+                    i += 1;
+                }
+            }
+        }
+
+        if code_points.is_empty() {
+            return Err(self.error("empty starred relation"));
+        }
+        Ok(code_points)
+    }
+
+    fn text(&mut self) -> Result<Box<[u32]>> {
+        Ok(self.text_with(false)?.into_boxed_slice())
+    }
+
+    // A run of literal characters, quoted text and escapes, ending at the next operator. Inside a
+    // starred relation an unquoted `-` marks a range and comes back as RANGE_MARK.
+    fn text_with(&mut self, ranges: bool) -> Result<Vec<u32>> {
+        let mut code_points = Vec::new();
+
+        loop {
+            self.skip_space();
+            let Some(c) = self.peek() else { break };
+
+            match c {
+                '&' | '<' | '=' | '/' | '|' | '[' | ']' => break,
+                '\'' => {
+                    self.next();
+                    if self.peek() == Some('\'') {
+                        self.next();
+                        code_points.push(u32::from('\''));
+                        continue;
+                    }
+                    loop {
+                        match self.next() {
+                            None => return Err(self.error("unterminated quote")),
+                            Some('\'') if self.peek() == Some('\'') => {
+                                self.next();
+                                code_points.push(u32::from('\''));
+                            }
+                            Some('\'') => break,
+                            Some(c) => code_points.push(u32::from(c)),
+                        }
+                    }
+                }
+                '\\' => {
+                    self.next();
+                    code_points.push(self.escape()?);
+                }
+                '-' if ranges => {
+                    self.next();
+                    code_points.push(RANGE_MARK);
+                }
+                _ if c.is_ascii_alphanumeric() || !c.is_ascii() => {
+                    self.next();
+                    code_points.push(u32::from(c));
+                }
+                _ => return Err(self.error(format!("syntax character '{c}' must be quoted"))),
+            }
+        }
+
+        Ok(code_points)
+    }
+
+    // After a backslash: \uhhhh, \Uhhhhhhhh, \x{h...}, \xhh, or any character taken literally
+    fn escape(&mut self) -> Result<u32> {
+        let digits = match self.next() {
+            Some('u') => 4,
+            Some('U') => 8,
+            Some('x') if self.eat("{") => {
+                let mut hex = String::new();
+                loop {
+                    match self.next() {
+                        Some('}') => break,
+                        Some(c) => hex.push(c),
+                        None => return Err(self.error("unterminated \\x{...}")),
+                    }
+                }
+                return self.code_point(&hex);
+            }
+            Some('x') => 2,
+            Some(c) => return Ok(u32::from(c)),
+            None => return Err(self.error("backslash at end of rules")),
+        };
+
+        let hex: String = (0..digits).filter_map(|_| self.next()).collect();
+        self.code_point(&hex)
+    }
+
+    fn code_point(&self, hex: &str) -> Result<u32> {
+        u32::from_str_radix(hex.trim(), 16)
+            .ok()
+            .filter(|&cp| char::from_u32(cp).is_some())
+            .ok_or_else(|| self.error(format!("invalid escape: {hex}")))
+    }
+}
+
+// Stands in for an unquoted hyphen in starred relations; it's not a valid code point
+const RANGE_MARK: u32 = u32::MAX;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cps(s: &str) -> Box<[u32]> {
+        s.chars().map(u32::from).collect()
+    }
+
+    #[test]
+    fn parse_rules() {
+        let data = "\
+[import de-u-co-phonebk]
+[caseFirst upper]
+# Danish
+&[before 1]ǀ < æ <<< Æ << ä
+&C < ch <<< Ch <<< CH
+&t <<< þ/h
+&a <*'-'\\u0062-d = '''x'
+&ァ <<< ァ|ー = ｧ | ー/x
+";
+        let rules = CollationRules::parse(Path::new("da.txt"), data).unwrap();
+
+        assert_eq!(rules.imports, ["de-u-co-phonebk"]);
+        assert_eq!(rules.settings, [("caseFirst".into(), "upper".into())]);
+        assert_eq!(rules.chains.len(), 5);
+
+        let chain = &rules.chains[0];
+        assert_eq!(chain.before, Some(Level::Primary));
+        assert_eq!(chain.reset, cps("ǀ"));
+        assert_eq!(chain.line, 4);
+        assert_eq!(chain.relations[1].level, Level::Tertiary);
+        assert_eq!(chain.relations[2].code_points, cps("ä"));
+
+        assert_eq!(rules.chains[1].relations[0].code_points, cps("ch"));
+        assert_eq!(rules.chains[2].relations[0].extension, cps("h"));
+
+        let starred: Vec<u32> = rules.chains[3].relations[..5]
+            .iter()
+            .map(|r| r.code_points[0])
+            .collect();
+        assert_eq!(starred, [0x2D, 0x62, 0x63, 0x64, 0x27]);
+        assert_eq!(rules.chains[3].relations[4].level, Level::Identical);
+        assert_eq!(rules.chains[3].relations[4].code_points, cps("'x"));
+
+        let prefixed = &rules.chains[4].relations;
+        assert_eq!(prefixed[0].prefix, cps("ァ"));
+        assert_eq!(prefixed[0].code_points, cps("ー"));
+        assert_eq!(prefixed[1].prefix, cps("ｧ"));
+        assert_eq!(prefixed[1].extension, cps("x"));
+        assert!(rules.chains[0].relations[0].prefix.is_empty());
+
+        let parse = |data| CollationRules::parse(Path::new("x"), data);
+        assert!(parse("&a < ").is_err());
+        assert!(parse("&a < b!").is_err());
+        assert!(parse("&a <<<< b").is_err());
+        assert!(parse("&a < b|").is_err());
+        assert!(parse("a < b").is_err());
+        assert!(parse("&[before 4]a < b").is_err());
+    }
+
+    #[test]
+    fn import_names() {
+        assert_eq!(rules_name("de-u-co-phonebk"), "de-phonebook");
+        assert_eq!(rules_name("es-u-co-trad"), "es-traditional");
+        assert_eq!(rules_name("sv-u-co-standard"), "sv");
+        assert_eq!(rules_name("und-u-co-search"), "und-search");
+        assert_eq!(rules_name("fi"), "fi");
+    }
+}
@@ -0,0 +1,337 @@
+#![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
+
+use crate::{
+    collation::unpack_weights,
+    error::{Error, Result},
+    implicit::ImplicitWeightsTable,
+    normalization::{DecompTable, FcdTable},
+    paths::{Paths, read_input, read_table},
+    trie::CollationTrieTable,
+    variable::VariableTable,
+};
+use feruca::Tailoring;
+use unicode_canonical_combining_class::get_canonical_combining_class as get_ccc;
+
+// Hangul syllables aren't in the decomposition table, since they decompose algorithmically
+const HANGUL_S_BASE: u32 = 0xAC00;
+const HANGUL_L_BASE: u32 = 0x1100;
+const HANGUL_V_BASE: u32 = 0x1161;
+const HANGUL_T_BASE: u32 = 0x11A7;
+const HANGUL_S_COUNT: u32 = 11_172;
+const HANGUL_V_COUNT: u32 = 21;
+const HANGUL_T_COUNT: u32 = 28;
+
+/// How variable collation elements (spaces, punctuation and symbols) are weighted
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VariableWeighting {
+    /// Variable elements are ignored at the first three levels, and their primaries are compared
+    /// at a fourth
+    #[default]
+    Shifted,
+    /// Variable elements are compared like any others
+    NonIgnorable,
+}
+
+/// The generated tables that feruca needs for one collation, which together make a reference
+/// implementation of UCA sort keys
+pub struct CollationTables {
+    pub decomp: DecompTable,
+    pub fcd: FcdTable,
+    pub variable: VariableTable,
+    pub trie: CollationTrieTable,
+    /// First collation element of each code point on the low fast path, as in `low.json`
+    pub low: Box<[u32]>,
+    pub implicit: ImplicitWeightsTable,
+}
+
+impl CollationTables {
+    /// Reads the tables for DUCET or CLDR root from the output directories
+    pub fn read(paths: &Paths, keys: Tailoring) -> Result<Self> {
+        if keys == Tailoring::Ducet {
+            Self::read_with(paths, "ducet", "low.json")
+        } else {
+            Self::read_with(paths, "cldr_root", "low_cldr.json")
+        }
+    }
+
+    /// Reads the tables for a trie under `tailoring/`, with its own low table if it has one
+    pub fn read_tailoring(paths: &Paths, name: &str) -> Result<Self> {
+        let low = format!("low_{name}.json");
+        let low = if paths.json(&low).is_file() {
+            low.as_str()
+        } else {
+            "low_cldr.json"
+        };
+
+        Self::read_with(paths, &format!("tailoring/{name}"), low)
+    }
+
+    fn read_with(paths: &Paths, trie: &str, low: &str) -> Result<Self> {
+        let low_path = paths.json(low);
+        let low = serde_json::from_str(&read_input(&low_path)?)
+            .map_err(|e| Error::serialization(&low_path, e))?;
+
+        Ok(Self {
+            decomp: read_table(&paths.out("decomp"))?,
+            fcd: read_table(&paths.out("fcd"))?,
+            variable: read_table(&paths.out("variable"))?,
+            trie: read_table(&paths.out(trie))?,
+            low,
+            implicit: read_table(&paths.out("implicit"))?,
+        })
+    }
+
+    /// A UCA sort key: the nonzero weights of each level in turn, with a zero between levels.
+    /// Shifted weighting adds a fourth level.
+    #[must_use]
+    pub fn sort_key(&self, chars: &[char], weighting: VariableWeighting) -> Vec<u16> {
+        // Text that is already FCD needs no normalization, since the tables map precomposed
+        // characters directly
+        let chars = if self.is_fcd(chars) {
+            decompose_hangul_syllables(chars)
+        } else {
+            self.nfd(chars)
+        };
+        let elements = self.collation_elements(&chars);
+
+        let shifted = weighting == VariableWeighting::Shifted;
+        let levels = if shifted { 4 } else { 3 };
+
+        // Without any variable or primary-ignorable code points, shifting changes nothing but to
+        // give each element a maximal fourth-level weight
+        let shifting = shifted && chars.iter().any(|&c| self.variable.contains(u32::from(c)));
+
+        let mut keys: [Vec<u16>; 4] = Default::default();
+        let mut after_variable = false;
+
+        for packed in elements {
+            let (variable, primary, secondary, tertiary) = unpack_weights(packed);
+            let mut weights = [primary, secondary, tertiary, 0];
+
+            if shifted && weights[..3] != [0; 3] {
+                weights[3] = u16::MAX;
+            }
+            if shifting && variable {
+                weights = [0, 0, 0, primary];
+                after_variable = true;
+            } else if shifting && primary == 0 && after_variable {
+                continue;
+            } else if primary != 0 {
+                after_variable = false;
+            }
+
+            for (key, weight) in keys.iter_mut().zip(weights) {
+                if weight != 0 {
+                    key.push(weight);
+                }
+            }
+        }
+
+        let mut sort_key = Vec::new();
+        for (level, key) in keys.iter().take(levels).enumerate() {
+            if level > 0 {
+                sort_key.push(0);
+            }
+            sort_key.extend(key);
+        }
+        sort_key
+    }
+
+    // Whether no combining mark follows one with a higher combining class, so that canonical
+    // reordering would have nothing to do
+    fn is_fcd(&self, chars: &[char]) -> bool {
+        let mut last_ccc = 0;
+
+        for &c in chars {
+            let fcd = match self.fcd.get(u32::from(c)) {
+                0 => u16::from(get_ccc(c) as u8) * 0x101,
+                fcd => fcd,
+            };
+            let [first_ccc, ccc] = fcd.to_be_bytes();
+            if first_ccc != 0 && first_ccc < last_ccc {
+                return false;
+            }
+            last_ccc = ccc;
+        }
+
+        true
+    }
+
+    // Full canonical decomposition, then canonical ordering of combining marks
+    fn nfd(&self, chars: &[char]) -> Vec<char> {
+        let mut nfd = Vec::with_capacity(chars.len());
+
+        for &c in chars {
+            let code_point = u32::from(c);
+            if (HANGUL_S_BASE..HANGUL_S_BASE + HANGUL_S_COUNT).contains(&code_point) {
+                nfd.extend(decompose_hangul(code_point));
+            } else if let Some(decomp) = self.decomp.get(code_point) {
+                nfd.extend(decomp.iter().filter_map(|&cp| char::from_u32(cp)));
+            } else {
+                nfd.push(c);
+            }
+        }
+
+        let mut start = 0;
+        while start < nfd.len() {
+            let len = nfd[start..]
+                .iter()
+                .take_while(|&&c| get_ccc(c) as u8 != 0)
+                .count();
+            nfd[start..start + len].sort_by_key(|&c| get_ccc(c) as u8);
+            start += len.max(1);
+        }
+
+        nfd
+    }
+
+    fn collation_elements(&self, nfd: &[char]) -> Vec<u32> {
+        let mut chars = nfd.to_vec();
+        let mut elements = Vec::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            // A prefix mapping whose context matches takes the place of anything else
+            if let Some((weights, consumed)) = self.trie.lookup_prefixed(&chars[..i], &chars[i..]) {
+                elements.extend_from_slice(weights);
+                i += consumed;
+                continue;
+            }
+
+            let Some((weights, consumed)) = self.trie.lookup(&chars[i..]) else {
+                let code_point = u32::from(chars[i]);
+                match self.low.get(usize::try_from(code_point).unwrap()) {
+                    Some(&low) => elements.push(low),
+                    None => elements.extend(self.implicit.weights(code_point)),
+                }
+                i += 1;
+                continue;
+            };
+
+            let mut matched = chars[i..i + consumed].to_vec();
+            let mut weights = weights;
+
+            // A contraction can also skip over combining marks, as long as none of them blocks
+            // the one that it takes
+            if self.trie.matches_discontiguously(u32::from(chars[i])) {
+                let mut j = i + consumed;
+                let mut last_skipped = 0;
+
+                while let Some(&c) = chars.get(j) {
+                    let ccc = get_ccc(c) as u8;
+                    if ccc == 0 {
+                        break;
+                    }
+
+                    matched.push(c);
+                    match self.trie.lookup(&matched) {
+                        Some((w, n)) if n == matched.len() && last_skipped < ccc => {
+                            weights = w;
+                            chars.remove(j);
+                        }
+                        _ => {
+                            matched.pop();
+                            last_skipped = ccc;
+                            j += 1;
+                        }
+                    }
+                }
+            }
+
+            elements.extend_from_slice(weights);
+            i += consumed;
+        }
+
+        elements
+    }
+}
+
+fn decompose_hangul_syllables(chars: &[char]) -> Vec<char> {
+    let mut decomposed = Vec::with_capacity(chars.len());
+    for &c in chars {
+        let code_point = u32::from(c);
+        if (HANGUL_S_BASE..HANGUL_S_BASE + HANGUL_S_COUNT).contains(&code_point) {
+            decomposed.extend(decompose_hangul(code_point));
+        } else {
+            decomposed.push(c);
+        }
+    }
+    decomposed
+}
+
+fn decompose_hangul(code_point: u32) -> impl Iterator<Item = char> {
+    let s_index = code_point - HANGUL_S_BASE;
+    let l = HANGUL_L_BASE + s_index / (HANGUL_V_COUNT * HANGUL_T_COUNT);
+    let v = HANGUL_V_BASE + (s_index % (HANGUL_V_COUNT * HANGUL_T_COUNT)) / HANGUL_T_COUNT;
+    let t = HANGUL_T_BASE + s_index % HANGUL_T_COUNT;
+
+    [l, v]
+        .into_iter()
+        .chain((t != HANGUL_T_BASE).then_some(t))
+        .filter_map(char::from_u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use VariableWeighting::{NonIgnorable, Shifted};
+
+    fn key(tables: &CollationTables, s: &str, weighting: VariableWeighting) -> Vec<u16> {
+        tables.sort_key(&s.chars().collect::<Vec<_>>(), weighting)
+    }
+
+    #[test]
+    fn orders_strings() {
+        for keys in [Tailoring::Ducet, Tailoring::default()] {
+            let tables = CollationTables::read(&Paths::default(), keys).unwrap();
+            let key = |s, weighting| key(&tables, s, weighting);
+
+            let ordered = ["a", "A", "á", "Á", "ab", "b", "ʒ", "α", "가", "각", "一"];
+            for pair in ordered.windows(2) {
+                assert!(
+                    key(pair[0], NonIgnorable) < key(pair[1], NonIgnorable),
+                    "{pair:?}"
+                );
+            }
+
+            // Precomposed and decomposed forms get the same key, whether or not they're FCD
+            assert_eq!(key("\u{1EC7}", Shifted), key("e\u{323}\u{302}", Shifted));
+            assert_eq!(
+                key("e\u{302}\u{323}", Shifted),
+                key("e\u{323}\u{302}", Shifted)
+            );
+            assert_eq!(
+                key("\u{AC01}", Shifted),
+                key("\u{1100}\u{1161}\u{11A8}", Shifted)
+            );
+
+            // Punctuation is ignored at the first three levels only when shifted
+            assert!(key("a-c", NonIgnorable) < key("ab", NonIgnorable));
+            assert!(key("ab", Shifted) < key("a-c", Shifted));
+            assert!(key("a-b", Shifted) < key("ab", Shifted));
+        }
+    }
+
+    #[test]
+    fn tailored_order() {
+        let paths = Paths::default();
+        let root = CollationTables::read(&paths, Tailoring::default()).unwrap();
+        let sv = CollationTables::read_tailoring(&paths, "sv").unwrap();
+        let es = CollationTables::read_tailoring(&paths, "es").unwrap();
+
+        assert!(key(&root, "å", Shifted) < key(&root, "z", Shifted));
+        for pair in ["z", "å", "Å", "ä", "ö"].windows(2) {
+            assert!(
+                key(&sv, pair[0], Shifted) < key(&sv, pair[1], Shifted),
+                "{pair:?}"
+            );
+        }
+        assert!(key(&es, "n", Shifted) < key(&es, "ñ", Shifted));
+        assert!(key(&es, "ñ", Shifted) < key(&es, "o", Shifted));
+
+        // Decomposed forms of tailored letters follow them
+        assert_eq!(key(&sv, "a\u{30A}", Shifted), key(&sv, "å", Shifted));
+        assert_eq!(key(&sv, "A\u{30A}", Shifted), key(&sv, "Å", Shifted));
+        assert_eq!(key(&es, "n\u{303}", Shifted), key(&es, "ñ", Shifted));
+    }
+}
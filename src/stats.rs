@@ -0,0 +1,210 @@
+#![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
+
+use crate::{
+    bundle::{BASE_PAGE, TrieBundle},
+    common::{CODE_POINT_COUNT, PAGE_SIZE, PAGE_WORDS},
+    diff::list_outputs,
+    error::{Error, Result},
+    implicit::ImplicitWeightsTable,
+    normalization::{DecompTable, FcdTable},
+    paths::read_table,
+    trie::{CollationTrieTable, ENTRY_CONTRACTION, ENTRY_MISSING, entry_tag},
+    variable::VariableTable,
+};
+use std::{fmt::Write, path::Path};
+
+/// Size and structure figures for one output table. Counts that don't apply to a kind of table
+/// are zero.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TableStats {
+    /// Path relative to the output directory, e.g. `cldr_root` or `tailoring/sv`
+    pub table: String,
+    /// Pages in the page index, covering the whole code point space
+    pub pages: usize,
+    /// Pages actually stored, once empty and duplicate pages are left out
+    pub unique_pages: usize,
+    /// Code points with a value, or ranges for the implicit table
+    pub entries: usize,
+    pub contraction_roots: usize,
+    pub edges: usize,
+    /// Code points in the longest contraction
+    pub max_contraction_len: usize,
+    /// Length of the shared pool of weights or decomposition values
+    pub pool_len: usize,
+    /// In-memory bytes saved by page dedupe and pool sharing, before postcard's varints
+    pub dedupe_saved: usize,
+    /// Size of the postcard file
+    pub file_size: u64,
+}
+
+/// Gathers statistics for every table in an output directory, in path order
+pub fn collect_stats(out_dir: &Path) -> Result<Vec<TableStats>> {
+    list_outputs(out_dir)?
+        .into_iter()
+        .map(|table| {
+            let path = out_dir.join(&table);
+            let mut stats = match table.as_str() {
+                "decomp" => decomp_stats(&read_table(&path)?),
+                "fcd" => fcd_stats(&read_table(&path)?),
+                "variable" => variable_stats(&read_table(&path)?),
+                "implicit" => implicit_stats(&read_table(&path)?),
+                "bundle" => bundle_stats(&read_table(&path)?),
+                _ => trie_stats(&read_table(&path)?),
+            };
+            stats.table = table;
+            stats.file_size = std::fs::metadata(&path)
+                .map_err(|e| Error::io(&path, e))?
+                .len();
+            Ok(stats)
+        })
+        .collect()
+}
+
+/// A line per table, with columns aligned
+#[must_use]
+pub fn stats_report(stats: &[TableStats]) -> String {
+    let width = stats.iter().map(|s| s.table.len()).max().unwrap_or(0);
+    let mut report = format!(
+        "{:width$} {:>11} {:>8} {:>6} {:>6} {:>3} {:>7} {:>9} {:>9}\n",
+        "table", "pages", "entries", "roots", "edges", "max", "pool", "saved", "size"
+    );
+
+    for s in stats {
+        writeln!(
+            report,
+            "{:width$} {:>11} {:>8} {:>6} {:>6} {:>3} {:>7} {:>9} {:>9}",
+            s.table,
+            format!("{}/{}", s.unique_pages, s.pages),
+            s.entries,
+            s.contraction_roots,
+            s.edges,
+            s.max_contraction_len,
+            s.pool_len,
+            s.dedupe_saved,
+            s.file_size
+        )
+        .unwrap();
+    }
+
+    report
+}
+
+fn trie_stats(table: &CollationTrieTable) -> TableStats {
+    let mappings = table.mappings();
+    let referenced: usize = mappings.iter().map(|(_, weights)| weights.len()).sum();
+    let unique_pages = table.entries.len() / PAGE_SIZE;
+
+    let code_points = 0..u32::try_from(CODE_POINT_COUNT).unwrap();
+    let tags: Vec<u64> = code_points.map(|cp| entry_tag(table.entry(cp))).collect();
+
+    TableStats {
+        pages: table.page_index.len(),
+        unique_pages,
+        entries: tags.iter().filter(|&&tag| tag != ENTRY_MISSING).count(),
+        contraction_roots: tags.iter().filter(|&&tag| tag == ENTRY_CONTRACTION).count(),
+        edges: table.edges.len(),
+        max_contraction_len: table
+            .contraction_meta
+            .iter()
+            .map(|meta| usize::from(meta.max_len))
+            .max()
+            .unwrap_or(0),
+        pool_len: table.weights.len(),
+        dedupe_saved: (table.page_index.len() - unique_pages) * PAGE_SIZE * 8
+            + (referenced - table.weights.len()) * 4,
+        ..TableStats::default()
+    }
+}
+
+// The base's figures, plus what the overlays store; dedupe counts the pages they share with it
+fn bundle_stats(bundle: &TrieBundle) -> TableStats {
+    let mut stats = trie_stats(&bundle.base);
+
+    for overlay in &bundle.overlays {
+        let shared = overlay.page_index.iter().filter(|&&page| page == BASE_PAGE);
+        stats.pages += overlay.page_index.len();
+        stats.unique_pages += overlay.entries.len() / PAGE_SIZE;
+        stats.edges += overlay.edges.len();
+        stats.pool_len += overlay.weights.len();
+        stats.dedupe_saved += shared.count() * PAGE_SIZE * 8;
+    }
+
+    stats
+}
+
+fn decomp_stats(table: &DecompTable) -> TableStats {
+    let code_points = 0..u32::try_from(CODE_POINT_COUNT).unwrap();
+    let decomps: Vec<&[u32]> = code_points.filter_map(|cp| table.get(cp)).collect();
+    let referenced: usize = decomps.iter().map(|decomp| decomp.len()).sum();
+    let unique_pages = table.entries.len() / PAGE_SIZE;
+
+    TableStats {
+        pages: table.page_index.len(),
+        unique_pages,
+        entries: decomps.len(),
+        pool_len: table.values.len(),
+        dedupe_saved: (table.page_index.len() - unique_pages) * PAGE_SIZE * 8
+            + (referenced - table.values.len()) * 4,
+        ..TableStats::default()
+    }
+}
+
+fn fcd_stats(table: &FcdTable) -> TableStats {
+    let unique_pages = table.pages.len() / PAGE_SIZE;
+    let code_points = 0..u32::try_from(CODE_POINT_COUNT).unwrap();
+
+    TableStats {
+        pages: table.page_index.len(),
+        unique_pages,
+        entries: code_points.filter(|&cp| table.get(cp) != 0).count(),
+        dedupe_saved: (table.page_index.len() - unique_pages) * PAGE_SIZE * 2,
+        ..TableStats::default()
+    }
+}
+
+fn variable_stats(table: &VariableTable) -> TableStats {
+    let unique_pages = table.pages.len() / PAGE_WORDS;
+    let code_points = 0..u32::try_from(CODE_POINT_COUNT).unwrap();
+
+    TableStats {
+        pages: table.page_index.len(),
+        unique_pages,
+        entries: code_points.filter(|&cp| table.contains(cp)).count(),
+        dedupe_saved: (table.page_index.len() - unique_pages) * PAGE_WORDS * 8,
+        ..TableStats::default()
+    }
+}
+
+fn implicit_stats(table: &ImplicitWeightsTable) -> TableStats {
+    TableStats {
+        entries: table.ranges.len(),
+        ..TableStats::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::Paths;
+
+    const PAGE_COUNT: usize = CODE_POINT_COUNT / PAGE_SIZE;
+
+    #[test]
+    fn root_stats() {
+        let stats = collect_stats(&Paths::default().out_dir).unwrap();
+        let stat = |table: &str| stats.iter().find(|s| s.table == table).unwrap();
+
+        let cldr = stat("cldr_root");
+        assert_eq!(cldr.pages, PAGE_COUNT);
+        assert!(cldr.unique_pages < cldr.pages);
+        assert!(cldr.contraction_roots > 0 && cldr.contraction_roots < cldr.entries);
+        assert!(cldr.edges >= cldr.contraction_roots);
+        assert_eq!(cldr.max_contraction_len, 3);
+        assert!(cldr.file_size > 0);
+
+        let variable = stat("variable");
+        assert!(variable.unique_pages < PAGE_COUNT && variable.entries > 0);
+        assert!(stat("implicit").entries > 0);
+        assert!(stats.iter().all(|s| s.table != "tailoring/manifest.json"));
+    }
+}
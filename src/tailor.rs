@@ -0,0 +1,502 @@
+#![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
+
+use crate::{
+    collation::{PrefixKey, PrefixMappings, low_from_mappings, pack_weights, unpack_weights},
+    delta::write_tailoring,
+    error::{Error, Result, SourceLine},
+    fractional::{IMPLICIT_FLOOR, SEC_COMMON, SEC_LIMIT, TER_COMMON, TER_LIMIT},
+    implicit::{ImplicitWeightsTable, build_implicit_table},
+    paths::{Paths, write_json},
+    reorder::{PrimaryReordering, collect_reorder_groups},
+    rules::{CollationRules, Level, RuleChain},
+};
+use rustc_hash::FxHashMap;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, hash_map::Entry},
+    hash::BuildHasher,
+};
+
+// Per level: the weight that stays put when others are inserted (a new secondary can't go below
+// the common one, which implicit weights use), and the first weight that the level can't hold.
+// Primaries from the implicit bases up don't move at all.
+const COMMON: [u16; 3] = [0, SEC_COMMON, TER_COMMON];
+const LIMIT: [u16; 3] = [IMPLICIT_FLOOR, SEC_LIMIT, TER_LIMIT];
+const LEVEL_NAMES: [&str; 3] = ["primary", "secondary", "tertiary"];
+
+/// Root mappings with a set of rules applied, in the shape the trie builder takes
+pub struct TailoredMappings {
+    pub singles: FxHashMap<u32, Box<[u32]>>,
+    pub multis: FxHashMap<Box<[u32]>, Box<[u32]>>,
+    pub prefixes: PrefixMappings,
+}
+
+// A weight as the rules see it: one from the root collation, or one that a relation inserted
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Weight {
+    Root(u16),
+    Inserted(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Element {
+    variable: bool,
+    weights: [Weight; 3],
+}
+
+impl Element {
+    const fn root(packed: u32) -> Self {
+        let (variable, primary, secondary, tertiary) = unpack_weights(packed);
+        Self {
+            variable,
+            weights: [
+                Weight::Root(primary),
+                Weight::Root(secondary),
+                Weight::Root(tertiary),
+            ],
+        }
+    }
+
+    // The weights above a level, which the weight at that level is only ever compared under: none
+    // for a primary, the primary for a secondary, and both for a tertiary. The rest are zero.
+    fn context(&self, l: usize) -> Context {
+        let mut context = [Weight::Root(0); 2];
+        context[..l].copy_from_slice(&self.weights[..l]);
+        context
+    }
+}
+
+type Context = [Weight; 2];
+
+// The weights inserted at one level under one context. Each is kept in a list after the root
+// weight that it follows, and they're only given numbers once all of the rules are in.
+#[derive(Default)]
+struct InsertedWeights {
+    after: BTreeMap<u16, Vec<usize>>,
+    bases: Vec<u16>,
+    roots: FxHashMap<u16, u32>,
+    numbers: Vec<u32>,
+}
+
+impl InsertedWeights {
+    fn insert_after(&mut self, weight: Weight) -> Weight {
+        match weight {
+            Weight::Root(base) => self.insert(base, 0),
+            Weight::Inserted(id) => self.insert(self.bases[id], self.position(id) + 1),
+        }
+    }
+
+    // Just before a root weight is just after the one below it, following anything already there
+    fn insert_before(&mut self, weight: Weight) -> Weight {
+        match weight {
+            Weight::Root(base) => {
+                let len = self.after.get(&(base - 1)).map_or(0, Vec::len);
+                self.insert(base - 1, len)
+            }
+            Weight::Inserted(id) => self.insert(self.bases[id], self.position(id)),
+        }
+    }
+
+    fn insert(&mut self, base: u16, index: usize) -> Weight {
+        let id = self.bases.len();
+        self.bases.push(base);
+        self.after.entry(base).or_default().insert(index, id);
+        Weight::Inserted(id)
+    }
+
+    fn position(&self, id: usize) -> usize {
+        self.after[&self.bases[id]]
+            .iter()
+            .position(|&other| other == id)
+            .unwrap()
+    }
+
+    // Inserted weights go into the gap after the weight that they follow, and root weights above
+    // it move up only once that's used up, as far as the next gap
+    fn number_all(&mut self, used: &BTreeSet<u16>) {
+        let mut weights = used.clone();
+        weights.extend(self.after.keys());
+
+        self.numbers = vec![0; self.bases.len()];
+        let mut next = 0;
+        for w in weights {
+            if used.contains(&w) {
+                let number = u32::from(w).max(next);
+                self.roots.insert(w, number);
+                next = number + 1;
+            } else {
+                // Only a base for `[before N]`, so its own number is free
+                next = next.max(u32::from(w));
+            }
+
+            for &id in self.after.get(&w).into_iter().flatten() {
+                self.numbers[id] = next;
+                next += 1;
+            }
+        }
+    }
+
+    fn number(&self, weight: Weight) -> u32 {
+        match weight {
+            Weight::Root(w) => self.roots[&w],
+            Weight::Inserted(id) => self.numbers[id],
+        }
+    }
+}
+
+struct Tailor<'a> {
+    singles: FxHashMap<u32, Box<[u32]>>,
+    multis: FxHashMap<Box<[u32]>, Box<[u32]>>,
+    implicit: &'a ImplicitWeightsTable,
+    tailored: FxHashMap<Box<[u32]>, Vec<Element>>,
+    // Relations with a context
+    prefixed: FxHashMap<PrefixKey, Vec<Element>>,
+    longest_tailored: usize,
+    longest_multi: usize,
+    inserted: [FxHashMap<Context, InsertedWeights>; 3],
+}
+
+impl Tailor<'_> {
+    // Collation elements for a string, taking the longest match at each position
+    fn elements(&self, code_points: &[u32]) -> Vec<Element> {
+        let mut elements = Vec::new();
+        let mut i = 0;
+
+        'outer: while i < code_points.len() {
+            let rest = &code_points[i..];
+
+            for len in (1..=self.longest_tailored.min(rest.len())).rev() {
+                if let Some(tailored) = self.tailored.get(&rest[..len]) {
+                    elements.extend_from_slice(tailored);
+                    i += len;
+                    continue 'outer;
+                }
+            }
+
+            for len in (2..=self.longest_multi.min(rest.len())).rev() {
+                if let Some(weights) = self.multis.get(&rest[..len]) {
+                    elements.extend(weights.iter().map(|&w| Element::root(w)));
+                    i += len;
+                    continue 'outer;
+                }
+            }
+
+            match self.singles.get(&rest[0]) {
+                Some(weights) => elements.extend(weights.iter().map(|&w| Element::root(w))),
+                None => elements.extend(self.implicit.weights(rest[0]).map(Element::root)),
+            }
+            i += 1;
+        }
+
+        elements
+    }
+
+    fn apply(&mut self, chain: &RuleChain) -> std::result::Result<(), String> {
+        let mut previous = self.elements(&chain.reset);
+
+        for (i, relation) in chain.relations.iter().enumerate() {
+            let before = if i == 0 { chain.before } else { None };
+            let elements = self.relate(&previous, relation.level, before)?;
+
+            let mut tailored = elements.clone();
+            tailored.extend(self.elements(&relation.extension));
+            if relation.prefix.is_empty() {
+                self.longest_tailored = self.longest_tailored.max(relation.code_points.len());
+                self.tailored.insert(relation.code_points.clone(), tailored);
+            } else {
+                let key = (relation.prefix.clone(), relation.code_points.clone());
+                self.prefixed.insert(key, tailored);
+            }
+
+            // The next relation follows this one, not counting the extension
+            previous = elements;
+        }
+
+        Ok(())
+    }
+
+    // The new element replaces the last one with a weight at the relation's level, and anything
+    // after that is dropped; lower levels get common weights
+    fn relate(
+        &mut self,
+        previous: &[Element],
+        level: Level,
+        before: Option<Level>,
+    ) -> std::result::Result<Vec<Element>, String> {
+        if before.is_some_and(|before| before != level) {
+            return Err("[before N] must be followed by a relation at level N".to_string());
+        }
+
+        let l = match level {
+            Level::Primary => 0,
+            Level::Secondary => 1,
+            Level::Tertiary => 2,
+            Level::Identical => return Ok(previous.to_vec()),
+        };
+
+        let i = previous
+            .iter()
+            .rposition(|e| e.weights[l] != Weight::Root(0))
+            .ok_or_else(|| format!("reset has no {} weight", LEVEL_NAMES[l]))?;
+        let mut element = previous[i];
+
+        if let Weight::Root(base) = element.weights[l] {
+            let anchor = if before.is_some() { base - 1 } else { base };
+            if anchor < COMMON[l] || (l == 0 && base >= IMPLICIT_FLOOR) {
+                return Err(format!(
+                    "can't insert a {} weight next to {base:04X}",
+                    LEVEL_NAMES[l]
+                ));
+            }
+        }
+
+        let inserted = self.inserted[l].entry(element.context(l)).or_default();
+        element.weights[l] = if before.is_some() {
+            inserted.insert_before(element.weights[l])
+        } else {
+            inserted.insert_after(element.weights[l])
+        };
+        for (weight, common) in element.weights.iter_mut().zip(COMMON).skip(l + 1) {
+            *weight = Weight::Root(common);
+        }
+
+        let mut elements = previous[..i].to_vec();
+        elements.push(element);
+        Ok(elements)
+    }
+
+    fn pack(&self, element: Element) -> std::result::Result<u32, String> {
+        let mut weights = [0u16; 3];
+
+        for (l, weight) in element.weights.into_iter().enumerate() {
+            weights[l] = match weight {
+                Weight::Root(w) if l == 0 && w >= IMPLICIT_FLOOR => w,
+                _ => {
+                    let number = self.number(element, l);
+                    u16::try_from(number)
+                        .ok()
+                        .filter(|&n| n < LIMIT[l])
+                        .ok_or_else(|| format!("too many {} weights", LEVEL_NAMES[l]))?
+                }
+            };
+        }
+
+        Ok(pack_weights(
+            element.variable,
+            weights[0],
+            weights[1],
+            weights[2],
+        ))
+    }
+
+    // Gives every weight its number once the rules are all in
+    fn number_all(&mut self) {
+        // Every root weight that the output can hold under each context that had insertions,
+        // including the common and zero weights of implicit mappings
+        let mut used: [FxHashMap<Context, BTreeSet<u16>>; 3] = Default::default();
+        for (l, used) in used.iter_mut().enumerate() {
+            for &context in self.inserted[l].keys() {
+                used.insert(context, BTreeSet::from([0, COMMON[l]]));
+            }
+        }
+        let root_elements = (self.singles.values())
+            .chain(self.multis.values())
+            .flat_map(|weights| weights.iter().map(|&w| Element::root(w)));
+        let tailored_elements = (self.tailored.values())
+            .chain(self.prefixed.values())
+            .flatten()
+            .copied();
+        for element in root_elements.chain(tailored_elements) {
+            for (l, used) in used.iter_mut().enumerate() {
+                if let Weight::Root(w) = element.weights[l]
+                    && let Some(used) = used.get_mut(&element.context(l))
+                {
+                    used.insert(w);
+                }
+            }
+        }
+        for (inserted, used) in self.inserted.iter_mut().zip(&used) {
+            for (context, inserted) in inserted {
+                inserted.number_all(&used[context]);
+            }
+        }
+    }
+
+    fn number(&self, element: Element, l: usize) -> u32 {
+        match (
+            self.inserted[l].get(&element.context(l)),
+            element.weights[l],
+        ) {
+            (Some(inserted), weight) => inserted.number(weight),
+            // Nothing was inserted among these weights, so they keep their root values
+            (None, Weight::Root(w)) => u32::from(w),
+            (None, Weight::Inserted(_)) => unreachable!("inserted weight without a context"),
+        }
+    }
+
+    fn pack_all(&self, weights: &[u32]) -> std::result::Result<Box<[u32]>, String> {
+        weights
+            .iter()
+            .map(|&w| self.pack(Element::root(w)))
+            .collect()
+    }
+}
+
+/// Applies a set of rules to the CLDR root mappings. Any `[reorder ...]` is applied first, so
+/// that the rules see the reordered primaries.
+pub fn apply_rules<S1: BuildHasher, S2: BuildHasher>(
+    paths: &Paths,
+    rules: &CollationRules,
+    cldr_singles: &HashMap<u32, Box<[u32]>, S1>,
+    cldr_multis: &HashMap<Box<[u32]>, Box<[u32]>, S2>,
+) -> Result<TailoredMappings> {
+    let reordering = if rules.reorder.is_empty() {
+        None
+    } else {
+        let groups = collect_reorder_groups(paths)?;
+        let reordering = PrimaryReordering::new(&groups, &rules.reorder)
+            .map_err(|message| Error::invalid(&rules.path, message))?;
+        Some(reordering)
+    };
+    let reorder = |weights: &[u32]| {
+        reordering
+            .as_ref()
+            .map_or_else(|| weights.into(), |r| r.map_weights(weights))
+    };
+
+    let implicit = build_implicit_table(paths)?;
+    let mut tailor = Tailor {
+        singles: cldr_singles.iter().map(|(&k, v)| (k, reorder(v))).collect(),
+        multis: cldr_multis
+            .iter()
+            .map(|(k, v)| (k.clone(), reorder(v)))
+            .collect(),
+        implicit: &implicit,
+        tailored: FxHashMap::default(),
+        prefixed: FxHashMap::default(),
+        longest_tailored: 0,
+        longest_multi: cldr_multis.keys().map(|k| k.len()).max().unwrap_or(0),
+        inserted: Default::default(),
+    };
+
+    for chain in &rules.chains {
+        tailor.apply(chain).map_err(|message| {
+            SourceLine {
+                file: &rules.path,
+                line: chain.line,
+            }
+            .error(message)
+        })?;
+    }
+
+    tailor.number_all();
+
+    let invalid = |message: String| Error::invalid(&rules.path, message);
+
+    let mut singles = FxHashMap::default();
+    for (&code_point, weights) in &tailor.singles {
+        singles.insert(code_point, tailor.pack_all(weights).map_err(invalid)?);
+    }
+    let mut multis = FxHashMap::default();
+    for (code_points, weights) in &tailor.multis {
+        multis.insert(
+            code_points.clone(),
+            tailor.pack_all(weights).map_err(invalid)?,
+        );
+    }
+
+    let pack_elements = |elements: &[Element]| {
+        elements
+            .iter()
+            .map(|&e| tailor.pack(e))
+            .collect::<std::result::Result<Box<[u32]>, String>>()
+            .map_err(invalid)
+    };
+
+    for (code_points, elements) in &tailor.tailored {
+        let weights = pack_elements(elements)?;
+        if let [code_point] = **code_points {
+            singles.insert(code_point, weights);
+        } else {
+            multis.insert(code_points.clone(), weights);
+        }
+    }
+
+    let mut prefixes = PrefixMappings::default();
+    for (key, elements) in &tailor.prefixed {
+        prefixes.insert(key.clone(), pack_elements(elements)?);
+    }
+
+    // A tailored contraction or prefix mapping needs a single mapping for its first code point,
+    // even if that's Han
+    let starts = (tailor.tailored.keys()).chain(tailor.prefixed.keys().map(|(_, cps)| cps));
+    for code_points in starts {
+        if let Entry::Vacant(entry) = singles.entry(code_points[0]) {
+            let weights = tailor.implicit.weights(code_points[0]);
+            entry.insert(tailor.pack_all(&weights).map_err(invalid)?);
+        }
+    }
+
+    Ok(TailoredMappings {
+        singles,
+        multis,
+        prefixes,
+    })
+}
+
+/// Writes a CLDR trie tailored by the rules in `collation/<name>.txt`, along with its delta and low
+/// table, and returns the mappings that went into it
+pub fn map_rules_trie<S1: BuildHasher, S2: BuildHasher>(
+    paths: &Paths,
+    name: &str,
+    cldr_singles: &HashMap<u32, Box<[u32]>, S1>,
+    cldr_multis: &HashMap<Box<[u32]>, Box<[u32]>, S2>,
+) -> Result<TailoredMappings> {
+    let rules = CollationRules::read(paths, name)?;
+    let mut mappings = apply_rules(paths, &rules, cldr_singles, cldr_multis)?;
+    write_tailoring(paths, name, cldr_singles, cldr_multis, &mut mappings)?;
+
+    // Prefix mappings are only in the trie, so the code points that they start can't be on the low
+    // fast path
+    let mut low = low_from_mappings(&mappings.singles, &mappings.multis);
+    for (_, code_points) in mappings.prefixes.keys() {
+        if let Some(slot) = low.get_mut(usize::try_from(code_points[0]).unwrap()) {
+            *slot = 0;
+        }
+    }
+    write_json(&paths.json(&format!("low_{name}.json")), &low.as_slice())?;
+
+    Ok(mappings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collation::{collect_multis, collect_singles};
+    use feruca::Tailoring;
+    use std::path::Path;
+
+    #[test]
+    fn changes_only_named_code_points() {
+        let paths = Paths::default();
+        let singles = collect_singles(&paths, Tailoring::default()).unwrap();
+        let multis = collect_multis(&paths, Tailoring::default()).unwrap();
+
+        let rules = CollationRules::parse(Path::new("es.txt"), "&N<ñ<<<Ñ").unwrap();
+        let mappings = apply_rules(&paths, &rules, &singles, &multis).unwrap();
+
+        let mut changed: Vec<u32> = (mappings.singles.iter())
+            .filter(|&(k, w)| singles.get(k) != Some(w))
+            .map(|(&k, _)| k)
+            .collect();
+        changed.sort_unstable();
+        assert_eq!(changed, [0xD1, 0xF1]);
+        assert_eq!(mappings.singles.len(), singles.len());
+        assert_eq!(mappings.multis, multis);
+
+        // ñ sorts after every n and before o, with Ñ a tertiary difference from it
+        let primary = |code_point| mappings.singles[&code_point][0] >> 16;
+        assert!(primary(0x6E) < primary(0xF1) && primary(0xF1) < primary(0x6F));
+        assert_eq!(primary(0xD1), primary(0xF1));
+        assert!(mappings.singles[&0xF1][0] < mappings.singles[&0xD1][0]);
+    }
+}
@@ -1,13 +1,16 @@
-#![allow(clippy::missing_panics_doc)]
+#![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
 
 use crate::{
-    collation::{collect_multis, collect_singles, unpack_code_points},
+    collation::{PrefixKey, PrefixMappings, collect_multis, collect_singles},
     common::{CODE_POINT_COUNT, PAGE_SIZE},
+    error::{Error, Result},
+    paths::{Paths, read_table, write_table},
 };
 use feruca::Tailoring;
 use rustc_hash::{FxHashMap, FxHashSet};
-use serde::Serialize;
-use std::{collections::HashMap, hash::BuildHasher};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, hash::BuildHasher, path::Path};
+use unicode_canonical_combining_class::get_canonical_combining_class_u32 as get_ccc;
 
 pub const ENTRY_MISSING: u64 = 0;
 pub const ENTRY_SIMPLE: u64 = 1;
@@ -25,23 +28,38 @@ const ENTRY_META_SHIFT: u64 = ENTRY_START_SHIFT + ENTRY_START_BITS;
 
 const NO_ROW: u32 = u32::MAX;
 
-#[derive(Serialize)]
+/// The trie that feruca reads, serialized with postcard
+///
+/// Fields are only ever appended, so that readers that predate one can still decode the fields
+/// before it. More data about contraction roots or edges goes in a parallel array at the end,
+/// since a field added to `ContractionMeta` or `ContractionEdge` would change the encoding of
+/// every element.
+#[derive(Deserialize, Serialize)]
 pub struct CollationTrieTable {
     pub page_index: Box<[u16]>,
     pub entries: Box<[u64]>,
     pub contraction_meta: Box<[ContractionMeta]>,
     pub edges: Box<[ContractionEdge]>,
     pub weights: Box<[u32]>,
+    /// For each contraction root, whether any edge below it, at any depth, is for a non-starter;
+    /// only then can a contraction match discontiguously (UCA S2.1.1–S2.1.3)
+    pub non_starters: Box<[bool]>,
+    /// For each edge, the canonical combining class of its code point, zero for a starter
+    pub edge_cccs: Box<[u8]>,
+    /// For each edge, whether any of its next edges is for a non-starter
+    pub next_non_starters: Box<[bool]>,
+    /// Sorted by code points, then by prefix
+    pub prefixes: Box<[PrefixMapping]>,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct ContractionMeta {
     pub first_edge: u32,
     pub edge_len: u16,
     pub max_len: u8,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct ContractionEdge {
     pub code_point: u32,
     pub next_first_edge: u32,
@@ -50,6 +68,202 @@ pub struct ContractionEdge {
     pub weight_len: u16,
 }
 
+/// Weights for code points that follow a given context, as from a rule `x|y`. They take precedence
+/// over the entry and contractions of the first code point, where the context matches.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PrefixMapping {
+    pub prefix: Box<[u32]>,
+    pub code_points: Box<[u32]>,
+    pub weight_start: u32,
+    pub weight_len: u16,
+}
+
+impl CollationTrieTable {
+    /// Decodes a table as written by `write_trie`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, postcard::Error> {
+        postcard::from_bytes(bytes)
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        read_table(path)
+    }
+
+    #[must_use]
+    pub fn entry(&self, code_point: u32) -> u64 {
+        let Ok(code_point) = usize::try_from(code_point) else {
+            return ENTRY_MISSING;
+        };
+        let Some(&page) = self.page_index.get(code_point / PAGE_SIZE) else {
+            return ENTRY_MISSING;
+        };
+        self.entries[usize::from(page) * PAGE_SIZE + code_point % PAGE_SIZE]
+    }
+
+    /// Every mapping in the table, in code point order, with each contraction after the single
+    /// mapping of its first code point
+    #[must_use]
+    pub fn mappings(&self) -> Vec<(Box<[u32]>, &[u32])> {
+        let mut mappings = Vec::new();
+
+        for code_point in 0..u32::try_from(CODE_POINT_COUNT).unwrap() {
+            let entry = self.entry(code_point);
+            if entry_tag(entry) == ENTRY_MISSING {
+                continue;
+            }
+
+            mappings.push((
+                vec![code_point].into(),
+                self.row(entry_start(entry), entry_len(entry)),
+            ));
+
+            if entry_tag(entry) == ENTRY_CONTRACTION {
+                let meta = &self.contraction_meta[usize::from(entry_meta_index(entry))];
+                self.collect_contractions(
+                    &[code_point],
+                    meta.first_edge,
+                    meta.edge_len,
+                    &mut mappings,
+                );
+            }
+        }
+
+        mappings
+    }
+
+    fn collect_contractions<'a>(
+        &'a self,
+        prefix: &[u32],
+        first_edge: u32,
+        edge_len: u16,
+        mappings: &mut Vec<(Box<[u32]>, &'a [u32])>,
+    ) {
+        let start = usize::try_from(first_edge).unwrap();
+        for edge in &self.edges[start..start + usize::from(edge_len)] {
+            let mut code_points = prefix.to_vec();
+            code_points.push(edge.code_point);
+
+            if edge.weight_len > 0 {
+                mappings.push((
+                    code_points.clone().into(),
+                    self.row(edge.weight_start, edge.weight_len),
+                ));
+            }
+            if edge.next_edge_len > 0 {
+                self.collect_contractions(
+                    &code_points,
+                    edge.next_first_edge,
+                    edge.next_edge_len,
+                    mappings,
+                );
+            }
+        }
+    }
+
+    fn row(&self, start: u32, len: u16) -> &[u32] {
+        let start = usize::try_from(start).unwrap();
+        &self.weights[start..start + usize::from(len)]
+    }
+
+    /// Every prefix mapping in the table, in order, as the prefix, the code points and the weights
+    pub fn prefix_mappings(&self) -> impl Iterator<Item = (&[u32], &[u32], &[u32])> {
+        self.prefixes.iter().map(|mapping| {
+            (
+                mapping.prefix.as_ref(),
+                mapping.code_points.as_ref(),
+                self.row(mapping.weight_start, mapping.weight_len),
+            )
+        })
+    }
+
+    /// Finds the prefix mapping for the code points at the start of `chars` whose prefix ends
+    /// `before`, preferring more code points and then a longer prefix, and returns its weights
+    /// with the number of code points they cover
+    #[must_use]
+    pub fn lookup_prefixed(&self, before: &[char], chars: &[char]) -> Option<(&[u32], usize)> {
+        let first = u32::from(*chars.first()?);
+        let start = self
+            .prefixes
+            .partition_point(|mapping| mapping.code_points[0] < first);
+
+        self.prefixes[start..]
+            .iter()
+            .take_while(|mapping| mapping.code_points[0] == first)
+            .filter(|mapping| {
+                ends_with(before, &mapping.prefix) && starts_with(chars, &mapping.code_points)
+            })
+            .max_by_key(|mapping| (mapping.code_points.len(), mapping.prefix.len()))
+            .map(|mapping| {
+                let weights = self.row(mapping.weight_start, mapping.weight_len);
+                (weights, mapping.code_points.len())
+            })
+    }
+
+    /// Whether a contraction starting with the code point can skip over unblocked non-starters
+    /// to match, per the contraction metadata
+    #[must_use]
+    pub fn matches_discontiguously(&self, code_point: u32) -> bool {
+        let entry = self.entry(code_point);
+        entry_tag(entry) == ENTRY_CONTRACTION
+            && self.non_starters[usize::from(entry_meta_index(entry))]
+    }
+
+    /// The weights for the longest match at the start of `chars`, and the number of chars that it
+    /// covers. Like feruca, this takes the simple weights of the first code point unless a
+    /// contraction edge with weights matches further on. Code points on the low fast path, and
+    /// those with implicit weights, have no entry and give `None`.
+    #[must_use]
+    pub fn lookup(&self, chars: &[char]) -> Option<(&[u32], usize)> {
+        let entry = self.entry(u32::from(*chars.first()?));
+
+        let mut found = match entry_tag(entry) {
+            ENTRY_SIMPLE | ENTRY_CONTRACTION => (entry_start(entry), entry_len(entry), 1),
+            _ => return None,
+        };
+
+        if entry_tag(entry) == ENTRY_CONTRACTION {
+            let meta = &self.contraction_meta[usize::from(entry_meta_index(entry))];
+            let max_len = chars.len().min(usize::from(meta.max_len));
+            let (mut first_edge, mut edge_len) = (meta.first_edge, meta.edge_len);
+
+            for (consumed, &c) in chars.iter().enumerate().take(max_len).skip(1) {
+                let start = usize::try_from(first_edge).unwrap();
+                let edges = &self.edges[start..start + usize::from(edge_len)];
+                let Ok(i) = edges.binary_search_by_key(&u32::from(c), |edge| edge.code_point)
+                else {
+                    break;
+                };
+
+                let edge = &edges[i];
+                if edge.weight_len > 0 {
+                    found = (edge.weight_start, edge.weight_len, consumed + 1);
+                }
+                if edge.next_edge_len == 0 {
+                    break;
+                }
+                (first_edge, edge_len) = (edge.next_first_edge, edge.next_edge_len);
+            }
+        }
+
+        let (start, len, consumed) = found;
+        Some((self.row(start, len), consumed))
+    }
+}
+
+fn starts_with(chars: &[char], code_points: &[u32]) -> bool {
+    chars.len() >= code_points.len()
+        && chars
+            .iter()
+            .zip(code_points)
+            .all(|(&c, &cp)| u32::from(c) == cp)
+}
+
+fn ends_with(chars: &[char], code_points: &[u32]) -> bool {
+    chars.len() >= code_points.len()
+        && (chars.iter().rev())
+            .zip(code_points.iter().rev())
+            .all(|(&c, &cp)| u32::from(c) == cp)
+}
+
 #[derive(Serialize)]
 struct WeightRow {
     start: u32,
@@ -97,52 +311,67 @@ impl Default for EdgeNode {
     }
 }
 
-pub fn map_trie(keys: Tailoring) {
+pub fn map_trie(paths: &Paths, keys: Tailoring) -> Result<()> {
     let cldr = keys != Tailoring::Ducet;
-    let singles = collect_singles(keys);
-    let multis = collect_multis(keys);
+    let singles = collect_singles(paths, keys)?;
+    let multis = collect_multis(paths, keys)?;
 
     let path_out = if cldr {
-        "bincode/cldr-46_1/cldr_root"
+        paths.out("cldr_root")
     } else {
-        "bincode/cldr-46_1/ducet"
+        paths.out("ducet")
     };
 
-    write_trie(path_out, &singles, &multis);
+    write_trie(&path_out, &singles, &multis)
 }
 
 pub fn map_cldr_trie<S1: BuildHasher, S2: BuildHasher>(
+    paths: &Paths,
     singles: &HashMap<u32, Box<[u32]>, S1>,
-    multis: &HashMap<u64, Box<[u32]>, S2>,
-) {
-    write_trie("bincode/cldr-46_1/cldr_root", singles, multis);
+    multis: &HashMap<Box<[u32]>, Box<[u32]>, S2>,
+) -> Result<()> {
+    write_trie(&paths.out("cldr_root"), singles, multis)
 }
 
 pub fn write_trie<S1: BuildHasher, S2: BuildHasher>(
-    path: &str,
+    path: &Path,
     singles: &HashMap<u32, Box<[u32]>, S1>,
-    multis: &HashMap<u64, Box<[u32]>, S2>,
-) {
-    let table = build_trie_table(singles, multis);
-    let bytes = postcard::to_allocvec(&table).unwrap();
-    std::fs::write(path, bytes).unwrap();
+    multis: &HashMap<Box<[u32]>, Box<[u32]>, S2>,
+) -> Result<()> {
+    let table = build_trie_table(singles, multis)?;
+    write_table(path, &table)
 }
 
-#[must_use]
 pub fn build_trie_table<S1: BuildHasher, S2: BuildHasher>(
     singles: &HashMap<u32, Box<[u32]>, S1>,
-    multis: &HashMap<u64, Box<[u32]>, S2>,
-) -> CollationTrieTable {
+    multis: &HashMap<Box<[u32]>, Box<[u32]>, S2>,
+) -> Result<CollationTrieTable> {
+    build_prefixed_trie_table(singles, multis, &PrefixMappings::default())
+}
+
+/// As `build_trie_table`, with prefix mappings too. Their first code points are kept off the low
+/// fast path, like contraction roots, and each needs a single mapping of its own.
+pub fn build_prefixed_trie_table<S1: BuildHasher, S2: BuildHasher, S3: BuildHasher>(
+    singles: &HashMap<u32, Box<[u32]>, S1>,
+    multis: &HashMap<Box<[u32]>, Box<[u32]>, S2>,
+    prefixes: &HashMap<PrefixKey, Box<[u32]>, S3>,
+) -> Result<CollationTrieTable> {
     let mut row_pool = RowPool::default();
     let mut contraction_roots: FxHashMap<u32, EdgeNode> = FxHashMap::default();
     let mut max_lens: FxHashMap<u32, u8> = FxHashMap::default();
 
-    for (&packed, weights) in multis {
-        let cps = unpack_code_points(packed);
+    // Sorted, so that the weight rows come out in the same order whatever the hasher
+    let mut multis: Vec<_> = multis.iter().collect();
+    multis.sort_unstable();
+
+    for (cps, weights) in multis {
+        let len = u8::try_from(cps.len()).map_err(|_| Error::ContractionTooLong {
+            code_point: cps[0],
+            len: cps.len(),
+        })?;
         let row = row_pool.insert(weights);
         let root = contraction_roots.entry(cps[0]).or_default();
         insert_contraction(root, &cps[1..], row);
-        let len = u8::try_from(cps.len()).unwrap();
         max_lens
             .entry(cps[0])
             .and_modify(|max_len| *max_len = (*max_len).max(len))
@@ -151,21 +380,28 @@ pub fn build_trie_table<S1: BuildHasher, S2: BuildHasher>(
 
     let mut entries = vec![ENTRY_MISSING; CODE_POINT_COUNT];
     let mut contraction_meta = Vec::new();
+    let mut non_starters = Vec::new();
     let mut edges = Vec::new();
+    let prefix_roots: FxHashSet<u32> = prefixes.keys().map(|(_, cps)| cps[0]).collect();
     let mut code_points: FxHashSet<u32> = singles.keys().copied().collect();
     code_points.extend(contraction_roots.keys().copied());
+    code_points.extend(prefix_roots.iter().copied());
 
     let mut code_points: Vec<u32> = code_points
         .into_iter()
-        .filter(|&code_point| !is_low_fast_path_code_point(code_point))
+        .filter(|code_point| {
+            !is_low_fast_path_code_point(*code_point)
+                || contraction_roots.contains_key(code_point)
+                || prefix_roots.contains(code_point)
+        })
         .collect();
     code_points.sort_unstable();
 
     for code_point in code_points {
         if let Some(root) = contraction_roots.get(&code_point) {
-            let simple_weights = singles.get(&code_point).unwrap_or_else(|| {
-                panic!("missing single mapping for contraction root U+{code_point:04X}")
-            });
+            let simple_weights = singles
+                .get(&code_point)
+                .ok_or(Error::MissingContractionPrefix { code_point })?;
             let simple_row = row_pool.insert(simple_weights);
             let simple_row = row_pool.get(simple_row);
             let first_edge = u32::try_from(edges.len()).unwrap();
@@ -176,13 +412,17 @@ pub fn build_trie_table<S1: BuildHasher, S2: BuildHasher>(
                 edge_len,
                 max_len: max_lens[&code_point],
             });
+            non_starters.push(has_non_starters(root));
             entries[usize::try_from(code_point).unwrap()] = pack_entry(
                 ENTRY_CONTRACTION,
                 simple_row.start,
                 simple_row.len,
                 meta_index,
             );
-        } else if let Some(weights) = singles.get(&code_point) {
+        } else {
+            let weights = singles
+                .get(&code_point)
+                .ok_or(Error::MissingContractionPrefix { code_point })?;
             let row = row_pool.insert(weights);
             let row = row_pool.get(row);
             entries[usize::try_from(code_point).unwrap()] =
@@ -190,18 +430,42 @@ pub fn build_trie_table<S1: BuildHasher, S2: BuildHasher>(
         }
     }
 
+    let mut prefixes: Vec<_> = prefixes.iter().collect();
+    prefixes.sort_unstable_by(|((a_prefix, a), _), ((b_prefix, b), _)| {
+        a.cmp(b).then_with(|| a_prefix.cmp(b_prefix))
+    });
+    let prefixes = prefixes
+        .into_iter()
+        .map(|((prefix, code_points), weights)| {
+            let row = row_pool.insert(weights);
+            let row = row_pool.get(row);
+            PrefixMapping {
+                prefix: prefix.clone(),
+                code_points: code_points.clone(),
+                weight_start: row.start,
+                weight_len: row.len,
+            }
+        })
+        .collect();
+
     let (page_index, entries) = dedupe_entry_pages(&entries);
+    let (edge_cccs, next_non_starters) = edge_classes(&edges);
 
-    CollationTrieTable {
+    Ok(CollationTrieTable {
         page_index,
         entries,
         contraction_meta: contraction_meta.into_boxed_slice(),
         edges: edges.into_boxed_slice(),
         weights: row_pool.weights.into_boxed_slice(),
-    }
+        non_starters: non_starters.into_boxed_slice(),
+        edge_cccs,
+        next_non_starters,
+        prefixes,
+    })
 }
 
-const fn pack_entry(tag: u64, start: u32, len: u16, meta_index: u16) -> u64 {
+#[must_use]
+pub const fn pack_entry(tag: u64, start: u32, len: u16, meta_index: u16) -> u64 {
     tag | ((len as u64) << ENTRY_LEN_SHIFT)
         | ((start as u64) << ENTRY_START_SHIFT)
         | ((meta_index as u64) << ENTRY_META_SHIFT)
@@ -227,7 +491,8 @@ pub const fn entry_meta_index(entry: u64) -> u16 {
     (entry >> ENTRY_META_SHIFT) as u16
 }
 
-fn dedupe_entry_pages(entries: &[u64]) -> (Box<[u16]>, Box<[u64]>) {
+#[must_use]
+pub fn dedupe_entry_pages(entries: &[u64]) -> (Box<[u16]>, Box<[u64]>) {
     let mut page_index = Vec::with_capacity(CODE_POINT_COUNT / PAGE_SIZE);
     let mut page_ids: FxHashMap<Box<[u64]>, u16> = FxHashMap::default();
     let mut deduped_entries = Vec::new();
@@ -296,6 +561,171 @@ fn write_edges(node: &EdgeNode, row_pool: &RowPool, edges: &mut Vec<ContractionE
     edge_len
 }
 
-const fn is_low_fast_path_code_point(code_point: u32) -> bool {
+// The combining class of each edge's code point, and whether any of its next edges has one
+fn edge_classes(edges: &[ContractionEdge]) -> (Box<[u8]>, Box<[bool]>) {
+    let cccs: Box<[u8]> = (edges.iter())
+        .map(|edge| get_ccc(edge.code_point) as u8)
+        .collect();
+    let next_non_starters = edges
+        .iter()
+        .map(|edge| {
+            let start = usize::try_from(edge.next_first_edge).unwrap();
+            let next = &cccs[start..start + usize::from(edge.next_edge_len)];
+            next.iter().any(|&ccc| ccc != 0)
+        })
+        .collect();
+
+    (cccs, next_non_starters)
+}
+
+fn has_non_starters(node: &EdgeNode) -> bool {
+    node.children
+        .iter()
+        .any(|(&code_point, child)| get_ccc(code_point) as u8 != 0 || has_non_starters(child))
+}
+
+#[must_use]
+pub const fn is_low_fast_path_code_point(code_point: u32) -> bool {
     code_point <= 0xB6 && code_point != 0x4C && code_point != 0x6C
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The layout before combining classes and prefix mappings were appended
+    #[derive(Deserialize)]
+    struct Original {
+        page_index: Box<[u16]>,
+        entries: Box<[u64]>,
+        contraction_meta: Box<[ContractionMeta]>,
+        edges: Box<[ContractionEdge]>,
+        weights: Box<[u32]>,
+    }
+
+    #[test]
+    fn lookup_round_trip() {
+        let singles: FxHashMap<u32, Box<[u32]>> = [
+            (0x4C, [1].into()),
+            (0xB7, [2].into()),
+            (0x0418, [3].into()),
+            (0x0306, [4].into()),
+        ]
+        .into_iter()
+        .collect();
+        let multis: FxHashMap<Box<[u32]>, Box<[u32]>> = [
+            ([0x4C, 0xB7].into(), [5, 6].into()),
+            ([0x0418, 0x0306, 0x0306].into(), [7].into()),
+            ([0x4C, 0xB7, 0x4C, 0xB7, 0x4C].into(), [8].into()),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut prefixes = PrefixMappings::default();
+        prefixes.insert(([0x0418].into(), [0x0306].into()), [9].into());
+        prefixes.insert(([0x4C, 0x0418].into(), [0x0306].into()), [10].into());
+
+        let table = build_prefixed_trie_table(&singles, &multis, &prefixes).unwrap();
+        let bytes = postcard::to_allocvec(&table).unwrap();
+        let table = CollationTrieTable::from_bytes(&bytes).unwrap();
+
+        assert_eq!(table.lookup(&['L', '·', 'x']), Some(([5, 6].as_slice(), 2)));
+        assert_eq!(table.lookup(&['L', 'x']), Some(([1].as_slice(), 1)));
+        assert_eq!(
+            table.lookup(&['L', '·', 'L', '·', 'L']),
+            Some(([8].as_slice(), 5))
+        );
+        assert_eq!(
+            table.lookup(&['L', '·', 'L', '·', 'x']),
+            Some(([5, 6].as_slice(), 2))
+        );
+        assert_eq!(table.lookup(&['·']), Some(([2].as_slice(), 1)));
+
+        // The two-code-point prefix of a longer contraction has no weights of its own
+        assert_eq!(
+            table.lookup(&['\u{418}', '\u{306}']),
+            Some(([3].as_slice(), 1))
+        );
+        assert_eq!(
+            table.lookup(&['\u{418}', '\u{306}', '\u{306}']),
+            Some(([7].as_slice(), 3))
+        );
+
+        assert_eq!(
+            table.mappings(),
+            [
+                (vec![0x4C].into(), [1].as_slice()),
+                (vec![0x4C, 0xB7].into(), [5, 6].as_slice()),
+                (vec![0x4C, 0xB7, 0x4C, 0xB7, 0x4C].into(), [8].as_slice()),
+                (vec![0xB7].into(), [2].as_slice()),
+                (vec![0x0306].into(), [4].as_slice()),
+                (vec![0x0418].into(), [3].as_slice()),
+                (vec![0x0418, 0x0306, 0x0306].into(), [7].as_slice()),
+            ]
+        );
+
+        // The longest prefix that the text before ends with wins; the contraction still applies
+        // from the start
+        assert_eq!(
+            table.lookup_prefixed(&['\u{418}'], &['\u{306}', 'x']),
+            Some(([9].as_slice(), 1))
+        );
+        assert_eq!(
+            table.lookup_prefixed(&['a', 'L', '\u{418}'], &['\u{306}']),
+            Some(([10].as_slice(), 1))
+        );
+        assert_eq!(table.lookup_prefixed(&['x'], &['\u{306}']), None);
+        assert_eq!(table.lookup_prefixed(&[], &['\u{306}']), None);
+        assert_eq!(table.prefix_mappings().count(), 2);
+
+        // Only the contractions on combining breve can skip over other marks
+        assert!(table.matches_discontiguously(0x0418));
+        assert!(!table.matches_discontiguously(0x4C));
+        assert!(!table.matches_discontiguously(0xB7));
+        let breve = table
+            .edges
+            .iter()
+            .position(|e| e.code_point == 0x0306)
+            .unwrap();
+        assert_eq!(table.edge_cccs[breve], 230);
+        assert!(table.next_non_starters[breve]);
+        assert!(
+            (table.edges.iter().zip(&table.edge_cccs))
+                .all(|(e, &ccc)| e.code_point == 0x0306 || ccc == 0)
+        );
+
+        // Low fast path, implicit and empty
+        assert_eq!(table.lookup(&['a']), None);
+        assert_eq!(table.lookup(&['\u{4E00}']), None);
+        assert_eq!(table.lookup(&[]), None);
+
+        // A reader of the original layout decodes everything before the appended fields
+        let original: Original = postcard::from_bytes(&bytes).unwrap();
+        assert_eq!(original.page_index, table.page_index);
+        assert_eq!(original.entries, table.entries);
+        assert_eq!(
+            original.contraction_meta.len(),
+            table.contraction_meta.len()
+        );
+        assert_eq!(original.edges.len(), table.edges.len());
+        assert_eq!(original.weights, table.weights);
+    }
+
+    #[test]
+    fn rejects_overlong_contraction() {
+        let mut singles: FxHashMap<u32, Box<[u32]>> = FxHashMap::default();
+        singles.insert(0x61, [1].into());
+        let mut multis: FxHashMap<Box<[u32]>, Box<[u32]>> = FxHashMap::default();
+        multis.insert(vec![0x61; 255].into(), [2].into());
+        assert!(build_trie_table(&singles, &multis).is_ok());
+
+        multis.insert(vec![0x61; 256].into(), [3].into());
+        assert!(matches!(
+            build_trie_table(&singles, &multis),
+            Err(Error::ContractionTooLong {
+                code_point: 0x61,
+                len: 256
+            })
+        ));
+    }
+}
@@ -1,80 +1,71 @@
-#![allow(clippy::missing_panics_doc, clippy::regex_creation_in_loops)]
+#![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
 
 use crate::{
-    collation::KEYS_DUCET,
+    allkeys::CollationElementTable,
     common::{CODE_POINT_COUNT, PAGE_SIZE, PAGE_WORDS, VARIABLE_EMPTY_PAGE},
-    regex,
+    error::Result,
+    paths::{Paths, write_json, write_table},
 };
+use feruca::Tailoring;
 use rustc_hash::{FxHashMap, FxHashSet};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::{collections::HashSet, hash::BuildHasher};
 
-#[derive(Serialize)]
+#[derive(Deserialize, Serialize)]
 pub struct VariableTable {
     pub page_index: Box<[u16]>,
     pub pages: Box<[u64]>,
 }
 
-pub fn map_variable() {
-    let mut set: FxHashSet<u32> = FxHashSet::default();
-
-    // We only need to use DUCET for this, since (as far as I can tell from testing) every code
-    // point in the CLDR table that has a variable weight or a zero primary weight, also has that
-    // in DUCET. But the inverse is not true.
-    'outer: for line in KEYS_DUCET.lines() {
-        if line.is_empty() || line.starts_with('@') || line.starts_with('#') {
-            continue;
+impl VariableTable {
+    /// Whether a code point has a variable weight or a zero primary weight
+    #[must_use]
+    pub fn contains(&self, code_point: u32) -> bool {
+        let page = self.page_index[usize::try_from(code_point >> 8).unwrap()];
+        if page == VARIABLE_EMPTY_PAGE {
+            return false;
         }
 
-        let mut split_at_semicolon = line.split(';');
-        let left_of_semicolon = split_at_semicolon.next().unwrap();
-        let right_of_semicolon = split_at_semicolon.next().unwrap();
-        let left_of_hash = right_of_semicolon.split('#').next().unwrap();
-
-        let mut points = Vec::new();
-        let re_key = regex!(r"[\dA-F]{4,5}");
-        for m in re_key.find_iter(left_of_semicolon) {
-            let as_u32 = u32::from_str_radix(m.as_str(), 16).unwrap();
-            points.push(as_u32);
-        }
-
-        // Here we're only looking for single-code-point lines
-        if points.len() > 1 {
-            continue;
-        }
-
-        let k = points[0];
-
-        let re_weights = regex!(r"[*.\dA-F]{15}");
-        let re_value = regex!(r"[\dA-F]{4}");
-
-        for m in re_weights.find_iter(left_of_hash) {
-            let weights_str = m.as_str();
-
-            let variable = weights_str.starts_with('*');
-
-            let mut vals = re_value.find_iter(weights_str);
-            let primary = u16::from_str_radix(vals.next().unwrap().as_str(), 16).unwrap();
-
-            // We're only interested in code points for which there is a variable weight or a zero
-            // primary weight.
-            if variable || primary == 0 {
-                set.insert(k);
-                continue 'outer;
-            }
-        }
+        let offset = usize::try_from(code_point & 0xFF).unwrap();
+        let word = self.pages[usize::from(page) * PAGE_WORDS + (offset >> 6)];
+        word & (1 << (offset & 0x3F)) != 0
     }
+}
+
+pub fn map_variable(paths: &Paths) -> Result<()> {
+    let set = collect_variable(paths)?;
 
     let mut sorted: Vec<u32> = set.iter().copied().collect();
     sorted.sort_unstable();
 
     // Write to JSON for debugging
-    let json_bytes = serde_json::to_vec(&sorted).unwrap();
-    std::fs::write("json/cldr-46_1/variable.json", json_bytes).unwrap();
+    write_json(&paths.json("variable.json"), &sorted)?;
 
     let table = build_variable_table(&set);
-    let bytes = postcard::to_allocvec(&table).unwrap();
-    std::fs::write("bincode/cldr-46_1/variable", bytes).unwrap();
+    write_table(&paths.out("variable"), &table)
+}
+
+pub fn collect_variable(paths: &Paths) -> Result<FxHashSet<u32>> {
+    let table = CollationElementTable::read(&paths.allkeys(Tailoring::Ducet))?;
+
+    // We only need to use DUCET for this, since (as far as I can tell from testing) every code
+    // point in the CLDR table that has a variable weight or a zero primary weight, also has that
+    // in DUCET. But the inverse is not true.
+    let set = table
+        .entries
+        .iter()
+        // Here we're only looking for single-code-point mappings
+        .filter_map(|entry| match *entry.code_points {
+            [k] => Some((k, &entry.elements)),
+            _ => None,
+        })
+        // We're only interested in code points for which there is a variable weight or a zero
+        // primary weight.
+        .filter(|(_, elements)| elements.iter().any(|e| e.variable || e.primary == 0))
+        .map(|(k, _)| k)
+        .collect();
+
+    Ok(set)
 }
 
 #[must_use]
@@ -0,0 +1,181 @@
+#![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
+
+use crate::{
+    error::Result,
+    trie::{CollationTrieTable, is_low_fast_path_code_point},
+};
+use rustc_hash::FxHashSet;
+use std::{collections::HashMap, fmt, hash::BuildHasher, path::Path};
+
+/// A mapping that doesn't come back out of a trie as it went in
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrieMismatch {
+    pub code_points: Box<[u32]>,
+    pub expected: Box<[u32]>,
+    /// The weights that the trie gave, and how many of the code points they covered
+    pub found: Option<(Box<[u32]>, usize)>,
+}
+
+impl fmt::Display for TrieMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: expected {}",
+            format_hex(&self.code_points),
+            format_weights(&self.expected)
+        )?;
+        match &self.found {
+            Some((weights, consumed)) if *consumed == self.code_points.len() => {
+                write!(f, ", found {}", format_weights(weights))
+            }
+            Some((weights, consumed)) => write!(
+                f,
+                ", found {} for only the first {consumed} of {} code points",
+                format_weights(weights),
+                self.code_points.len()
+            ),
+            None => write!(f, ", found no entry"),
+        }
+    }
+}
+
+/// Looks up every single and multi in a trie, returning those that don't resolve to the weights
+/// they were built from
+///
+/// Low code points that feruca reads from its low table instead are skipped, unless they start a
+/// contraction; but since that table holds one collation element per code point, any of them that
+/// maps to more than one is reported as missing.
+#[must_use]
+pub fn verify_trie<S1: BuildHasher, S2: BuildHasher>(
+    table: &CollationTrieTable,
+    singles: &HashMap<u32, Box<[u32]>, S1>,
+    multis: &HashMap<Box<[u32]>, Box<[u32]>, S2>,
+) -> Vec<TrieMismatch> {
+    let contraction_roots: FxHashSet<u32> =
+        multis.keys().map(|code_points| code_points[0]).collect();
+
+    let singles = singles
+        .iter()
+        .map(|(&code_point, weights)| (vec![code_point].into_boxed_slice(), weights));
+    let multis = multis
+        .iter()
+        .map(|(code_points, weights)| (code_points.clone(), weights));
+
+    let mut mismatches: Vec<TrieMismatch> = singles
+        .chain(multis)
+        .filter_map(|(code_points, weights)| {
+            let low = code_points.len() == 1
+                && is_low_fast_path_code_point(code_points[0])
+                && !contraction_roots.contains(&code_points[0]);
+            if low && weights.len() == 1 {
+                return None;
+            }
+
+            let chars: Vec<char> = code_points
+                .iter()
+                .map(|&cp| char::from_u32(cp).unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect();
+            let found = table.lookup(&chars);
+            if !low && found == Some((weights, code_points.len())) {
+                return None;
+            }
+
+            Some(TrieMismatch {
+                code_points,
+                expected: weights.clone(),
+                found: found.map(|(weights, consumed)| (weights.into(), consumed)),
+            })
+        })
+        .collect();
+
+    mismatches.sort_unstable_by(|a, b| a.code_points.cmp(&b.code_points));
+    mismatches
+}
+
+/// Reads back a trie as written by `write_trie` and checks it with `verify_trie`
+pub fn verify_trie_file<S1: BuildHasher, S2: BuildHasher>(
+    path: &Path,
+    singles: &HashMap<u32, Box<[u32]>, S1>,
+    multis: &HashMap<Box<[u32]>, Box<[u32]>, S2>,
+) -> Result<Vec<TrieMismatch>> {
+    let table = CollationTrieTable::read(path)?;
+    Ok(verify_trie(&table, singles, multis))
+}
+
+fn format_hex(code_points: &[u32]) -> String {
+    let hex: Vec<String> = code_points.iter().map(|cp| format!("{cp:04X}")).collect();
+    hex.join(" ")
+}
+
+fn format_weights(weights: &[u32]) -> String {
+    let hex: Vec<String> = weights.iter().map(|w| format!("{w:08X}")).collect();
+    format!("[{}]", hex.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collation::{collect_multis, collect_singles},
+        paths::Paths,
+        trie::build_trie_table,
+    };
+    use feruca::Tailoring;
+    use rustc_hash::FxHashMap;
+
+    fn round_trip(
+        singles: &FxHashMap<u32, Box<[u32]>>,
+        multis: &FxHashMap<Box<[u32]>, Box<[u32]>>,
+    ) -> CollationTrieTable {
+        let table = build_trie_table(singles, multis).unwrap();
+        let bytes = postcard::to_allocvec(&table).unwrap();
+        CollationTrieTable::from_bytes(&bytes).unwrap()
+    }
+
+    #[test]
+    fn root_tables_round_trip() {
+        let paths = Paths::default();
+
+        for keys in [Tailoring::Ducet, Tailoring::default()] {
+            let singles = collect_singles(&paths, keys).unwrap();
+            let multis = collect_multis(&paths, keys).unwrap();
+            let table = round_trip(&singles, &multis);
+
+            let mismatches = verify_trie(&table, &singles, &multis);
+            assert!(mismatches.is_empty(), "{}", mismatches[0]);
+        }
+    }
+
+    #[test]
+    fn reports_mismatches() {
+        let singles: FxHashMap<u32, Box<[u32]>> = [
+            (0x41, [1, 2].into()),
+            (0x0418, [3].into()),
+            (0x0306, [4].into()),
+        ]
+        .into_iter()
+        .collect();
+        let mut multis: FxHashMap<Box<[u32]>, Box<[u32]>> = FxHashMap::default();
+        multis.insert([0x0418, 0x0306].into(), [5].into());
+        let table = round_trip(&singles, &multis);
+
+        let mut changed = multis;
+        changed.insert([0x0418, 0x0306].into(), [6].into());
+        changed.insert([0x0418, 0x0308].into(), [7].into());
+
+        let mismatches = verify_trie(&table, &singles, &changed);
+        assert_eq!(mismatches.len(), 3);
+        assert_eq!(
+            mismatches[0].to_string(),
+            "0041: expected [00000001 00000002], found no entry"
+        );
+        assert_eq!(
+            mismatches[1].to_string(),
+            "0418 0306: expected [00000006], found [00000005]"
+        );
+        assert_eq!(
+            mismatches[2].to_string(),
+            "0418 0308: expected [00000007], found [00000003] for only the first 1 of 2 code points"
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use std::{fmt, str::FromStr};
+
+/// The Unicode/CLDR data releases checked in under `unicode-data`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DataVersion {
+    Unicode14,
+    Unicode15,
+    Cldr43,
+    Cldr44,
+    #[default]
+    Cldr46_1,
+}
+
+/// How collation weights were serialized in the older, map-based table layout
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LegacyLayout {
+    /// Each set of weights is a struct of `bool, u16, u16, u16` (Unicode 14)
+    StructWeights,
+    /// Each set of weights is packed into a `u32`, as by `pack_weights`
+    PackedWeights,
+}
+
+impl DataVersion {
+    pub const ALL: [Self; 5] = [
+        Self::Unicode14,
+        Self::Unicode15,
+        Self::Cldr43,
+        Self::Cldr44,
+        Self::Cldr46_1,
+    ];
+
+    #[must_use]
+    pub const fn dir_name(self) -> &'static str {
+        match self {
+            Self::Unicode14 => "14",
+            Self::Unicode15 => "15",
+            Self::Cldr43 => "cldr-43",
+            Self::Cldr44 => "cldr-44",
+            Self::Cldr46_1 => "cldr-46_1",
+        }
+    }
+
+    /// The map-based layout that feruca used for this release, if any
+    #[must_use]
+    pub const fn legacy_layout(self) -> Option<LegacyLayout> {
+        match self {
+            Self::Unicode14 => Some(LegacyLayout::StructWeights),
+            Self::Unicode15 | Self::Cldr43 | Self::Cldr44 => Some(LegacyLayout::PackedWeights),
+            Self::Cldr46_1 => None,
+        }
+    }
+
+    /// Whether the CLDR primaries are moved by `BUMP` and `SHIFT`. The gaps that this opens are
+    /// only used by tailorings built against CLDR 46.1, and the constants are specific to it.
+    #[must_use]
+    pub const fn adjusts_cldr_primaries(self) -> bool {
+        matches!(self, Self::Cldr46_1)
+    }
+}
+
+impl fmt::Display for DataVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.dir_name())
+    }
+}
+
+impl FromStr for DataVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|version| version.dir_name() == s)
+            .ok_or_else(|| format!("unknown data version: {s}"))
+    }
+}